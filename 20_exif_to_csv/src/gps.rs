@@ -0,0 +1,328 @@
+/// Which coordinate is being read, as that decides the hemisphere letters
+/// and range it may have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    Latitude,
+    Longitude,
+}
+
+impl Axis {
+    fn limit(self) -> f64 {
+        match self {
+            Axis::Latitude => 90.0,
+            Axis::Longitude => 180.0,
+        }
+    }
+
+    /// The sign a hemisphere letter gives, if it belongs to this axis.
+    fn sign(self, hemisphere: char) -> Option<f64> {
+        match (self, hemisphere.to_ascii_uppercase()) {
+            (Axis::Latitude, 'N') | (Axis::Longitude, 'E') => Some(1.0),
+            (Axis::Latitude, 'S') | (Axis::Longitude, 'W') => Some(-1.0),
+            _ => None,
+        }
+    }
+}
+
+/// A coordinate as signed decimal degrees, from any of the ways exiftool
+/// and cameras write one:
+///
+/// - decimal degrees, as exiftool's `-n` gives: `-122.4194`
+/// - degrees, minutes and seconds: `37 deg 46' 29.64" N`, `37°46'29.64"N`
+/// - degrees and decimal minutes: `37 deg 46.494' N`
+/// - XMP's `37,46.494N` and `37,46,29.64N`
+///
+/// The hemisphere may come before or after, as a letter or a word such as
+/// `South`.
+pub fn coordinate(text: &str, axis: Axis) -> Option<f64> {
+    let mut hemisphere = None;
+    let mut numbers = Vec::new();
+    for word in words(text) {
+        match word {
+            Word::Number(number) => numbers.push(number),
+            Word::Name(name) if name.eq_ignore_ascii_case("deg") => {}
+            Word::Name(name) if hemisphere.is_none() && is_hemisphere(&name) => {
+                hemisphere = Some(axis.sign(name.chars().next()?)?);
+            }
+            Word::Name(_) => return None,
+        }
+    }
+
+    let (&first, rest) = numbers.split_first()?;
+    if rest.len() > 2 || rest.iter().any(|&part| !(0.0..=60.0).contains(&part)) {
+        return None;
+    }
+    let magnitude = rest
+        .iter()
+        .zip([60.0, 3600.0])
+        .fold(first.abs(), |degrees, (part, scale)| degrees + part / scale);
+    let sign = hemisphere.unwrap_or(if first < 0.0 { -1.0 } else { 1.0 });
+    (magnitude <= axis.limit()).then_some(sign * magnitude)
+}
+
+/// `GPSPosition`, latitude then longitude: `37 deg 46' 29.64" N, 122 deg
+/// 25' 10.00" W`, or `37.7749 -122.4194` with `-n`.
+pub fn position(text: &str) -> Option<(f64, f64)> {
+    let (latitude, longitude) = match text.split_once(',') {
+        // XMP style uses commas inside each coordinate, so isn't split here
+        Some((latitude, longitude)) if !latitude.trim_end().ends_with(char::is_numeric) => {
+            (latitude, longitude)
+        }
+        _ => {
+            // after the latitude's hemisphere, or else between two numbers
+            match text.find(['N', 'S']) {
+                Some(at) if at > 0 => {
+                    let word = text[at..].find(|c: char| !c.is_alphabetic());
+                    text.split_at(word.map_or(text.len(), |end| at + end))
+                }
+                _ => {
+                    let words: Vec<&str> = text.split_whitespace().collect();
+                    let [latitude, longitude] = words[..] else {
+                        return None;
+                    };
+                    (latitude, longitude)
+                }
+            }
+        }
+    };
+    Some((
+        coordinate(latitude, Axis::Latitude)?,
+        coordinate(longitude, Axis::Longitude)?,
+    ))
+}
+
+/// Apply a `GPSLatitudeRef` or `GPSLongitudeRef` (`N`, `South`, ...) to a
+/// coordinate. The reference decides the sign whatever the coordinate's
+/// own, so a hemisphere given in both isn't applied twice.
+pub fn with_reference(coordinate: f64, reference: &str, axis: Axis) -> f64 {
+    match reference.trim().chars().next().and_then(|c| axis.sign(c)) {
+        Some(sign) => sign * coordinate.abs(),
+        None => coordinate,
+    }
+}
+
+/// Metres above sea level from `GPSAltitude`, which may be a number, a
+/// rational such as XMP's `1234/10`, or exiftool's `123.4 m Above Sea
+/// Level`, and `GPSAltitudeRef`, which is `0` or `1`, or spelled out.
+pub fn altitude(text: &str, reference: Option<&str>) -> Option<f64> {
+    let mut metres = None;
+    let mut below = false;
+    for word in text.split_whitespace() {
+        match word {
+            "m" | "Above" | "Sea" | "Level" => {}
+            "Below" => below = true,
+            _ if metres.is_none() => metres = Some(number(word)?),
+            _ => return None,
+        }
+    }
+    let metres = metres?;
+    match reference.map(str::trim) {
+        Some("1") => Some(-metres.abs()),
+        Some(reference) if reference.starts_with("Below") => Some(-metres.abs()),
+        Some(_) => Some(metres.abs()),
+        None if below => Some(-metres.abs()),
+        None => Some(metres),
+    }
+}
+
+/// A decimal or a rational.
+fn number(text: &str) -> Option<f64> {
+    let value = match text.split_once('/') {
+        Some((numerator, denominator)) => {
+            numerator.parse::<f64>().ok()? / denominator.parse::<f64>().ok()?
+        }
+        None => text.parse().ok()?,
+    };
+    value.is_finite().then_some(value)
+}
+
+fn is_hemisphere(name: &str) -> bool {
+    ["N", "S", "E", "W", "North", "South", "East", "West"]
+        .iter()
+        .any(|hemisphere| name.eq_ignore_ascii_case(hemisphere))
+}
+
+enum Word {
+    Number(f64),
+    Name(String),
+}
+
+/// Numbers and names in `text`, with `°'"`, commas and spaces between.
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_ascii_digit() || c == '.' || c == '-' || c == '+' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek() {
+                if !(c.is_ascii_digit()
+                    || c == '.'
+                    || (number.is_empty() && (c == '-' || c == '+')))
+                {
+                    break;
+                }
+                number.push(c);
+                chars.next();
+            }
+            match number.parse() {
+                Ok(number) => words.push(Word::Number(number)),
+                Err(_) => words.push(Word::Name(number)),
+            }
+        } else if c.is_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphabetic()) {
+                name.push(c);
+                chars.next();
+            }
+            words.push(Word::Name(name));
+        } else {
+            chars.next();
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(actual: Option<f64>, expected: Option<f64>, input: &str) {
+        match (actual, expected) {
+            (Some(actual), Some(expected)) => {
+                assert!(
+                    (actual - expected).abs() < 1e-9,
+                    "{input:?}: {actual} != {expected}"
+                )
+            }
+            _ => assert_eq!(actual, expected, "{input:?}"),
+        }
+    }
+
+    #[test]
+    fn coordinates() {
+        let cases: &[(&str, Axis, Option<f64>)] = &[
+            ("-122.4194", Axis::Longitude, Some(-122.4194)),
+            ("37.7749", Axis::Latitude, Some(37.7749)),
+            // degrees, minutes and seconds
+            ("37 deg 46' 29.64\" N", Axis::Latitude, Some(37.7749)),
+            ("37°46'29.64\"N", Axis::Latitude, Some(37.7749)),
+            ("37 deg 46.494' N", Axis::Latitude, Some(37.7749)),
+            // south and west are negative, as a letter or a word, before
+            // or after
+            (
+                "33 deg 52' 4.00\" S",
+                Axis::Latitude,
+                Some(-(33.0 + 52.0 / 60.0 + 4.0 / 3600.0)),
+            ),
+            (
+                "S 33 52 4",
+                Axis::Latitude,
+                Some(-(33.0 + 52.0 / 60.0 + 4.0 / 3600.0)),
+            ),
+            (
+                "122 deg 25' 10.00\" W",
+                Axis::Longitude,
+                Some(-(122.0 + 25.0 / 60.0 + 10.0 / 3600.0)),
+            ),
+            ("151.2093 East", Axis::Longitude, Some(151.2093)),
+            ("0.5 west", Axis::Longitude, Some(-0.5)),
+            // XMP's degrees and decimal minutes, or DMS, with commas
+            ("37,46.494N", Axis::Latitude, Some(37.7749)),
+            (
+                "122,25.1667W",
+                Axis::Longitude,
+                Some(-(122.0 + 25.1667 / 60.0)),
+            ),
+            ("37,46,29.64N", Axis::Latitude, Some(37.7749)),
+            // a hemisphere of the other axis, too many parts, minutes past
+            // 60 or degrees out of range
+            ("37 46 29.64 E", Axis::Latitude, None),
+            ("1 2 3 4", Axis::Latitude, None),
+            ("37 61 0 N", Axis::Latitude, None),
+            ("91", Axis::Latitude, None),
+            ("181 W", Axis::Longitude, None),
+            ("abc", Axis::Latitude, None),
+            ("", Axis::Latitude, None),
+        ];
+        for &(input, axis, expected) in cases {
+            assert_near(coordinate(input, axis), expected, input);
+        }
+    }
+
+    #[test]
+    fn references() {
+        let cases: &[(f64, &str, Axis, f64)] = &[
+            (33.5, "S", Axis::Latitude, -33.5),
+            (33.5, "South", Axis::Latitude, -33.5),
+            // a coordinate that's already signed isn't flipped back
+            (-33.5, "S", Axis::Latitude, -33.5),
+            (-33.5, "N", Axis::Latitude, 33.5),
+            (122.4, "W", Axis::Longitude, -122.4),
+            (122.4, " w ", Axis::Longitude, -122.4),
+            // not this axis's, or missing
+            (122.4, "S", Axis::Longitude, 122.4),
+            (-122.4, "", Axis::Longitude, -122.4),
+        ];
+        for &(value, reference, axis, expected) in cases {
+            assert_eq!(
+                with_reference(value, reference, axis),
+                expected,
+                "{value} {reference:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn positions() {
+        let cases: &[(&str, Option<(f64, f64)>)] = &[
+            ("37.7749 -122.4194", Some((37.7749, -122.4194))),
+            ("37.7749, -122.4194", Some((37.7749, -122.4194))),
+            (
+                "37 deg 46' 29.64\" N, 122 deg 25' 10.00\" W",
+                Some((37.7749, -(122.0 + 25.0 / 60.0 + 10.0 / 3600.0))),
+            ),
+            (
+                "33 deg 52' 4.00\" S 151 deg 12' 33.48\" E",
+                Some((-(33.0 + 52.0 / 60.0 + 4.0 / 3600.0), 151.2093)),
+            ),
+            (
+                "37,46.494N 122,25.1667W",
+                Some((37.7749, -(122.0 + 25.1667 / 60.0))),
+            ),
+            ("37.7749", None),
+            ("37.7749 -122.4194 10", None),
+            ("95 0", None),
+        ];
+        for &(input, expected) in cases {
+            let actual = position(input);
+            assert_near(actual.map(|p| p.0), expected.map(|p| p.0), input);
+            assert_near(actual.map(|p| p.1), expected.map(|p| p.1), input);
+        }
+    }
+
+    #[test]
+    fn altitudes() {
+        let cases: &[(&str, Option<&str>, Option<f64>)] = &[
+            ("123.4", None, Some(123.4)),
+            ("1234/10", None, Some(123.4)),
+            ("123.4 m Above Sea Level", None, Some(123.4)),
+            ("12 m Below Sea Level", None, Some(-12.0)),
+            // a ref of 1 is below sea level, 0 above
+            ("12", Some("1"), Some(-12.0)),
+            ("1234/10", Some("1"), Some(-123.4)),
+            ("12", Some("0"), Some(12.0)),
+            ("12", Some("Below Sea Level"), Some(-12.0)),
+            ("12", Some("Above Sea Level"), Some(12.0)),
+            // the ref wins over the text's own sign
+            ("-12", Some("0"), Some(12.0)),
+            ("12 m Below Sea Level", Some("1"), Some(-12.0)),
+            ("1/0", None, None),
+            ("twelve", None, None),
+            ("12 15", None, None),
+            ("", None, None),
+        ];
+        for &(text, reference, expected) in cases {
+            assert_near(altitude(text, reference), expected, text);
+        }
+    }
+}
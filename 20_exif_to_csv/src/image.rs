@@ -0,0 +1,178 @@
+use crate::xmp;
+use crate::Record;
+use chrono::{DateTime, Local};
+use exif::{Exif, In, Tag};
+use serde_json::Value;
+use std::fs::{self, File};
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+use walkdir::WalkDir;
+
+/// What we look for in directories: JPEG, HEIF, PNG, WebP, TIFF, and RAW
+/// formats. Most RAW files are TIFF underneath; ORF and RW2 aren't quite,
+/// so theirs is read from XMP alone.
+const EXTENSIONS: &[&str] = &[
+    "jpg", "jpeg", "heic", "heif", "avif", "png", "webp", "tif", "tiff", "dng", "cr2", "nef",
+    "nrw", "arw", "srw", "orf", "rw2", "pef",
+];
+
+/// `paths` with each directory replaced by the images under it, in name
+/// order. Files named directly are kept whatever their extension.
+pub fn image_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        for entry in WalkDir::new(path).sort_by_file_name() {
+            let entry = entry.map_err(io::Error::from)?;
+            if entry.file_type().is_file() && is_image(entry.path()) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| EXTENSIONS.contains(&extension.to_ascii_lowercase().as_str()))
+}
+
+/// The same tags exiftool's `-n -json` would give for `path`: GPS and
+/// capture times from its EXIF, or failing that its XMP, and the file's
+/// own dates.
+pub fn read(path: &Path) -> io::Result<Record> {
+    let metadata = fs::metadata(path)?;
+    let file_date = |time: SystemTime| {
+        let time: DateTime<Local> = time.into();
+        time.format("%Y:%m:%d %H:%M:%S%:z").to_string()
+    };
+    let mut record = Record {
+        // not every filesystem records when a file was created
+        file_create_date: metadata.created().ok().map(file_date),
+        file_modify_date: Some(file_date(metadata.modified()?)),
+        ..Record::default()
+    };
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let exif = exif::Reader::new()
+        .continue_on_error(true)
+        .read_from_container(&mut reader)
+        // keep whatever could be read from a damaged IFD
+        .or_else(|e| e.distill_partial_result(|_| {}));
+    match exif {
+        Ok(exif) => {
+            record.gps_latitude = coordinate(&exif, Tag::GPSLatitude, Tag::GPSLatitudeRef, b'S');
+            record.gps_longitude = coordinate(&exif, Tag::GPSLongitude, Tag::GPSLongitudeRef, b'W');
+            record.gps_altitude = altitude(&exif);
+            record.date_time_original = ascii(&exif, Tag::DateTimeOriginal);
+            record.offset_time_original = ascii(&exif, Tag::OffsetTimeOriginal);
+            record.create_date = ascii(&exif, Tag::DateTimeDigitized);
+            record.offset_time_digitized = ascii(&exif, Tag::OffsetTimeDigitized);
+            record.gps_date_stamp = ascii(&exif, Tag::GPSDateStamp);
+            record.gps_time_stamp = gps_time(&exif);
+        }
+        // PNGs and edited files often have only XMP, and ORF and RW2 have
+        // EXIF the reader doesn't recognise
+        Err(exif::Error::NotFound(_) | exif::Error::InvalidFormat(_)) => {}
+        Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e.to_string())),
+    }
+
+    let no_gps = record.gps_latitude.is_none() || record.gps_longitude.is_none();
+    if no_gps || record.date_time_original.is_none() {
+        if let Some(packet) = xmp::read(path)? {
+            // the same tags, as text, which `Record` reads either way
+            let property = |name| xmp::property(&packet, name).map(str::to_string);
+            if no_gps {
+                record.gps_latitude = property("exif:GPSLatitude").map(Value::from);
+                record.gps_longitude = property("exif:GPSLongitude").map(Value::from);
+                record.gps_altitude = property("exif:GPSAltitude").map(Value::from);
+                record.gps_altitude_ref = property("exif:GPSAltitudeRef").map(Value::from);
+            }
+            // XMP dates carry their offset, as in `2021-03-04T10:11:12-08:00`
+            if record.date_time_original.is_none() {
+                record.date_time_original =
+                    property("exif:DateTimeOriginal").or_else(|| property("photoshop:DateCreated"));
+            }
+            if record.create_date.is_none() {
+                record.create_date = property("xmp:CreateDate");
+            }
+        }
+    }
+    Ok(record)
+}
+
+/// Degrees, minutes and seconds as signed decimal degrees, negative when
+/// the reference is `negative` (`S` or `W`).
+fn coordinate(exif: &Exif, tag: Tag, reference: Tag, negative: u8) -> Option<Value> {
+    let exif::Value::Rational(dms) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    if dms.is_empty() {
+        return None;
+    }
+    let degrees: f64 = dms
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, scale)| part.to_f64() / scale)
+        .sum();
+    let sign = match exif
+        .get_field(reference, In::PRIMARY)
+        .map(|field| &field.value)
+    {
+        Some(exif::Value::Ascii(refs))
+            if refs.first().and_then(|r| r.first()) == Some(&negative) =>
+        {
+            -1.0
+        }
+        _ => 1.0,
+    };
+    // a zero denominator makes it NaN
+    degrees.is_finite().then(|| Value::from(sign * degrees))
+}
+
+/// Metres, negative when `GPSAltitudeRef` is 1 for below sea level.
+fn altitude(exif: &Exif) -> Option<Value> {
+    let exif::Value::Rational(metres) = &exif.get_field(Tag::GPSAltitude, In::PRIMARY)?.value
+    else {
+        return None;
+    };
+    let metres = metres.first()?.to_f64();
+    let below = exif
+        .get_field(Tag::GPSAltitudeRef, In::PRIMARY)
+        .and_then(|field| field.value.get_uint(0))
+        == Some(1);
+    metres
+        .is_finite()
+        .then(|| Value::from(if below { -metres } else { metres }))
+}
+
+/// An ASCII tag such as `DateTimeOriginal`, as written.
+fn ascii(exif: &Exif, tag: Tag) -> Option<String> {
+    let exif::Value::Ascii(values) = &exif.get_field(tag, In::PRIMARY)?.value else {
+        return None;
+    };
+    let text = String::from_utf8_lossy(values.first()?);
+    let text = text.trim_end_matches('\0').trim();
+    (!text.is_empty()).then(|| text.to_string())
+}
+
+/// `GPSTimeStamp`'s hours, minutes and seconds as `H:M:S`.
+fn gps_time(exif: &Exif) -> Option<Value> {
+    let exif::Value::Rational(hms) = &exif.get_field(Tag::GPSTimeStamp, In::PRIMARY)?.value else {
+        return None;
+    };
+    let [hours, minutes, seconds] = &hms[..] else {
+        return None;
+    };
+    Some(Value::from(format!(
+        "{}:{}:{}",
+        hours.to_f64(),
+        minutes.to_f64(),
+        seconds.to_f64()
+    )))
+}
@@ -0,0 +1,319 @@
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::io::{self, BufRead};
+
+/// A place in the input, and which record it's in.
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+    /// 1-based
+    pub record: usize,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "line {}, column {} (record {})",
+            self.line, self.column, self.record
+        )
+    }
+}
+
+/// Read JSON objects from `input` one at a time, calling `each` with each
+/// one parsed as a `T`, or why it couldn't be. Takes exiftool's `-json`
+/// arrays, whether on one line or pretty-printed, as well as objects one
+/// per line (NDJSON) or simply one after another. Only the current object
+/// is held in memory.
+pub fn read_records<T: DeserializeOwned>(
+    mut input: impl BufRead,
+    mut each: impl FnMut(Position, Result<T, String>),
+) -> io::Result<()> {
+    let mut scanner = Scanner::default();
+    loop {
+        let chunk = input.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        for &byte in chunk {
+            scanner.push(byte, &mut each);
+        }
+        let len = chunk.len();
+        input.consume(len);
+    }
+    if !scanner.nesting.is_empty() {
+        each(scanner.start, Err("unexpected end of input".to_string()));
+    }
+    Ok(())
+}
+
+/// Splits the input into top-level objects by tracking nesting, strings
+/// and escapes, so braces inside values don't confuse it.
+struct Scanner {
+    line: usize,
+    column: usize,
+    records: usize,
+    /// Inside the top-level array exiftool wraps its output in
+    in_array: bool,
+    /// The closing bracket of each level the current object is nested to;
+    /// empty between objects
+    nesting: Vec<u8>,
+    in_string: bool,
+    escaped: bool,
+    /// Skipping the rest of a line that isn't JSON
+    skipping: bool,
+    object: Vec<u8>,
+    start: Position,
+}
+
+impl Default for Scanner {
+    fn default() -> Scanner {
+        Scanner {
+            line: 1,
+            column: 0,
+            records: 0,
+            in_array: false,
+            nesting: Vec::new(),
+            in_string: false,
+            escaped: false,
+            skipping: false,
+            object: Vec::new(),
+            start: Position {
+                line: 1,
+                column: 1,
+                record: 0,
+            },
+        }
+    }
+}
+
+impl Scanner {
+    fn push<T: DeserializeOwned>(
+        &mut self,
+        byte: u8,
+        each: &mut impl FnMut(Position, Result<T, String>),
+    ) {
+        if byte == b'\n' {
+            self.line += 1;
+            self.column = 0;
+        } else {
+            self.column += 1;
+        }
+
+        if self.nesting.is_empty() {
+            self.between_objects(byte, each);
+            return;
+        }
+
+        self.object.push(byte);
+        if self.in_string {
+            if self.escaped {
+                self.escaped = false;
+            } else if byte == b'\\' {
+                self.escaped = true;
+            } else if byte == b'"' {
+                self.in_string = false;
+            }
+            return;
+        }
+        match byte {
+            b'"' => self.in_string = true,
+            b'{' => self.nesting.push(b'}'),
+            b'[' => self.nesting.push(b']'),
+            b'}' | b']' => {
+                if self.nesting.pop() != Some(byte) {
+                    // a mismatched bracket would swallow every later
+                    // record, so end this one here, where serde_json will
+                    // report it, and carry on from the next line
+                    self.nesting.clear();
+                    self.skipping = true;
+                }
+                if self.nesting.is_empty() {
+                    match serde_json::from_slice(&self.object) {
+                        Ok(record) => each(self.start, Ok(record)),
+                        Err(e) => each(self.error_position(&e), Err(message(&e))),
+                    }
+                    self.object.clear();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn between_objects<T>(&mut self, byte: u8, each: &mut impl FnMut(Position, Result<T, String>)) {
+        if self.skipping {
+            self.skipping = byte != b'\n';
+            return;
+        }
+        match byte {
+            b'{' => {
+                self.records += 1;
+                self.start = Position {
+                    line: self.line,
+                    column: self.column,
+                    record: self.records,
+                };
+                self.nesting.push(b'}');
+                self.object.push(byte);
+            }
+            b'[' if !self.in_array => self.in_array = true,
+            b']' if self.in_array => self.in_array = false,
+            b',' if self.in_array => {}
+            _ if byte.is_ascii_whitespace() => {}
+            _ => {
+                // a record that isn't an object, or a stray line such as a
+                // warning; report it and carry on from the next line
+                self.records += 1;
+                let position = Position {
+                    line: self.line,
+                    column: self.column,
+                    record: self.records,
+                };
+                each(
+                    position,
+                    Err(format!("expected an object, found `{}`", byte as char)),
+                );
+                self.skipping = byte != b'\n';
+            }
+        }
+    }
+    /// Where in the whole input `error`, found in the current object, is.
+    fn error_position(&self, error: &serde_json::Error) -> Position {
+        let (line, column) = match error.line() {
+            1 => (self.start.line, self.start.column + error.column() - 1),
+            line => (self.start.line + line - 1, error.column()),
+        };
+        Position {
+            line,
+            column,
+            ..self.start
+        }
+    }
+}
+
+/// serde_json's message without its position, which is within the object.
+fn message(error: &serde_json::Error) -> String {
+    let message = error.to_string();
+    let position = format!(" at line {} column {}", error.line(), error.column());
+    match message.strip_suffix(&position) {
+        Some(message) => message.to_string(),
+        None => message,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Deserialize)]
+    struct Record {
+        a: i64,
+    }
+
+    /// A record's (line, column, record), and its `a` or its error.
+    type Read = ((usize, usize, usize), Result<i64, String>);
+
+    fn read(input: &str) -> Vec<Read> {
+        let mut out = Vec::new();
+        read_records(
+            input.as_bytes(),
+            |position: Position, record: Result<Record, String>| {
+                out.push((
+                    (position.line, position.column, position.record),
+                    record.map(|record| record.a),
+                ))
+            },
+        )
+        .expect("reading from a slice");
+        out
+    }
+
+    #[test]
+    fn arrays_and_streams() {
+        let cases: &[(&str, &[i64])] = &[
+            ("", &[]),
+            ("[]", &[]),
+            (r#"[{"a":1},{"a":2}]"#, &[1, 2]),
+            (
+                "[\n  {\n    \"a\": 1\n  },\n  {\n    \"a\": 2\n  }\n]\n",
+                &[1, 2],
+            ),
+            ("{\"a\":1}\n{\"a\":2}\n", &[1, 2]),
+            (r#"{"a":1}{"a":2} {"a":3}"#, &[1, 2, 3]),
+            // braces and quotes inside strings don't end the object
+            (r#"[{"a":1,"b":"}{\"]"},{"a":2,"c":{"d":[1,{}]}}]"#, &[1, 2]),
+            // one array after another, as from two exiftool runs
+            (
+                r#"[{"a":1}]
+[{"a":2}]"#,
+                &[1, 2],
+            ),
+        ];
+        for (input, expected) in cases {
+            let records: Vec<i64> = read(input)
+                .into_iter()
+                .map(|(_, record)| record.unwrap_or_else(|e| panic!("{input:?}: {e}")))
+                .collect();
+            assert_eq!(&records, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn errors_are_placed_in_the_whole_input() {
+        let cases: &[(&str, (usize, usize, usize), &str)] = &[
+            // the record's own first line adds its start column
+            (r#"{"a":1} {"a":"x"}"#, (1, 16, 2), "invalid type"),
+            // later lines of the record are as they are
+            (
+                "[\n  {\"a\":1},\n  {\n    \"a\": true\n  }\n]",
+                (4, 13, 2),
+                "invalid type",
+            ),
+            ("{\"a\":1}\n{\"b\":2}\n", (2, 7, 2), "missing field `a`"),
+            ("{\"a\":1}\n{\"a\":", (2, 1, 2), "unexpected end of input"),
+            // a mismatched bracket ends the record
+            (
+                "{\"a\":1}\n{\"a\":1,\"b\":[}\n{\"a\":2}",
+                (2, 13, 2),
+                "expected value",
+            ),
+            (
+                "Warning: no file\n{\"a\":1}",
+                (1, 1, 1),
+                "expected an object, found `W`",
+            ),
+        ];
+        for (input, position, error) in cases {
+            let records = read(input);
+            let Some((at, Err(message))) = records.iter().find(|(_, record)| record.is_err())
+            else {
+                panic!("{input:?}: no error in {records:?}");
+            };
+            assert_eq!(at, position, "{input:?}");
+            assert!(message.starts_with(error), "{input:?}: {message}");
+            assert!(!message.contains(" at line "), "{message}");
+        }
+    }
+
+    #[test]
+    fn reading_carries_on_after_a_bad_record() {
+        let input = "{\"a\":1}\nnot json at all {\"a\":9}\n{\"a\":[}\n{\"a\":2}\n5\n{\"a\":3}\n";
+        let records: Vec<(usize, Result<i64, String>)> = read(input)
+            .into_iter()
+            .map(|((_, _, record), result)| (record, result.map_err(|_| String::new())))
+            .collect();
+        assert_eq!(
+            records,
+            [
+                (1, Ok(1)),
+                (2, Err(String::new())),
+                (3, Err(String::new())),
+                (4, Ok(2)),
+                (5, Err(String::new())),
+                (6, Ok(3)),
+            ]
+        );
+    }
+}
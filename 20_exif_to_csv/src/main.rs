@@ -1,97 +1,463 @@
+mod gps;
+mod image;
+mod json;
+mod project;
+mod time;
+mod xmp;
+
+use chrono::{DateTime, FixedOffset};
+use rayon::prelude::*;
 use serde::Deserialize;
+use serde_json::Value;
 use std::env;
-use std::io::{self, BufRead};
-use chrono::{DateTime, FixedOffset, Datelike};
+use std::io;
+use std::path::PathBuf;
+use std::process;
 
-#[derive(Deserialize, Debug)]
+/// The tags we use from one image. Each may be a number or text, as
+/// exiftool gives numbers with `-n` and formatted text without.
+#[derive(Deserialize, Debug, Default)]
 struct Record {
-    GPSLatitude: Option<f64>,
-    GPSLongitude: Option<f64>,
-    FileModifyDate: Option<String>,
+    #[serde(rename = "GPSLatitude")]
+    gps_latitude: Option<Value>,
+    #[serde(rename = "GPSLatitudeRef")]
+    gps_latitude_ref: Option<Value>,
+    #[serde(rename = "GPSLongitude")]
+    gps_longitude: Option<Value>,
+    #[serde(rename = "GPSLongitudeRef")]
+    gps_longitude_ref: Option<Value>,
+    /// Both coordinates, when exiftool's composite tags are included
+    #[serde(rename = "GPSPosition")]
+    gps_position: Option<Value>,
+    #[serde(rename = "GPSAltitude")]
+    gps_altitude: Option<Value>,
+    #[serde(rename = "GPSAltitudeRef")]
+    gps_altitude_ref: Option<Value>,
+    #[serde(rename = "DateTimeOriginal")]
+    date_time_original: Option<String>,
+    #[serde(rename = "OffsetTimeOriginal")]
+    offset_time_original: Option<String>,
+    #[serde(rename = "CreateDate")]
+    create_date: Option<String>,
+    #[serde(rename = "OffsetTimeDigitized")]
+    offset_time_digitized: Option<String>,
+    #[serde(rename = "GPSDateStamp")]
+    gps_date_stamp: Option<String>,
+    #[serde(rename = "GPSTimeStamp")]
+    gps_time_stamp: Option<Value>,
+    #[serde(rename = "FileCreateDate")]
+    file_create_date: Option<String>,
+    #[serde(rename = "FileModifyDate")]
+    file_modify_date: Option<String>,
 }
 
-fn normalize(v: f64, old_min: f64, old_max: f64, new_min: f64, new_max: f64) -> f64 {
-    if old_max == old_min {
-        return new_min;
+impl Record {
+    fn latitude(&self) -> Option<f64> {
+        self.coordinate(
+            &self.gps_latitude,
+            &self.gps_latitude_ref,
+            gps::Axis::Latitude,
+        )
+    }
+
+    fn longitude(&self) -> Option<f64> {
+        self.coordinate(
+            &self.gps_longitude,
+            &self.gps_longitude_ref,
+            gps::Axis::Longitude,
+        )
+    }
+
+    /// From the coordinate's own tag and its reference, or else from
+    /// `GPSPosition`.
+    fn coordinate(
+        &self,
+        value: &Option<Value>,
+        reference: &Option<Value>,
+        axis: gps::Axis,
+    ) -> Option<f64> {
+        match value
+            .as_ref()
+            .and_then(|value| gps::coordinate(&text(value), axis))
+        {
+            Some(coordinate) => Some(match reference {
+                Some(reference) => gps::with_reference(coordinate, &text(reference), axis),
+                None => coordinate,
+            }),
+            None => {
+                let (latitude, longitude) = gps::position(&text(self.gps_position.as_ref()?))?;
+                Some(match axis {
+                    gps::Axis::Latitude => latitude,
+                    gps::Axis::Longitude => longitude,
+                })
+            }
+        }
+    }
+
+    /// Metres, negative below sea level.
+    fn altitude(&self) -> Option<f64> {
+        let reference = self.gps_altitude_ref.as_ref().map(text);
+        gps::altitude(&text(self.gps_altitude.as_ref()?), reference.as_deref())
+    }
+
+    /// When the photo was taken, and which tags said so, such as
+    /// `DateTimeOriginal+OffsetTimeOriginal`. Capture times come first,
+    /// taking their offset from the matching `OffsetTime` tag or else the
+    /// nautical zone at the GPS position (`+GPS~nautical`); then the GPS
+    /// clock, which is UTC; then, as a last resort, the file's own dates,
+    /// which change whenever it's copied.
+    fn timestamp(&self) -> Option<(DateTime<FixedOffset>, String)> {
+        let captured = [
+            (
+                &self.date_time_original,
+                "DateTimeOriginal",
+                &self.offset_time_original,
+                "OffsetTimeOriginal",
+            ),
+            (
+                &self.create_date,
+                "CreateDate",
+                &self.offset_time_digitized,
+                "OffsetTimeDigitized",
+            ),
+        ];
+        for (value, name, offset, offset_name) in captured {
+            let Some((local, own_offset)) = value.as_deref().and_then(time::parse) else {
+                continue;
+            };
+            let (zone, source) = if let Some(zone) = own_offset {
+                (zone, name.to_string())
+            } else if let Some(zone) = offset.as_deref().and_then(time::offset) {
+                (zone, format!("{name}+{offset_name}"))
+            } else if let Some(longitude) = self.longitude() {
+                (
+                    time::nautical_zone(longitude),
+                    format!("{name}+GPS~nautical"),
+                )
+            } else {
+                // nothing to go on, so taken as UTC
+                (FixedOffset::east_opt(0)?, name.to_string())
+            };
+            if let Some(timestamp) = time::at(local, zone) {
+                return Some((timestamp, source));
+            }
+        }
+
+        if let (Some(date), Some(clock)) = (&self.gps_date_stamp, &self.gps_time_stamp) {
+            if let Some(timestamp) = time::gps(date, &text(clock)) {
+                return Some((timestamp, "GPSDateStamp+GPSTimeStamp".to_string()));
+            }
+        }
+
+        let files = [
+            (&self.file_create_date, "FileCreateDate"),
+            (&self.file_modify_date, "FileModifyDate"),
+        ];
+        files.into_iter().find_map(|(value, name)| {
+            let (local, zone) = time::parse(value.as_deref()?)?;
+            let timestamp = time::at(local, zone.unwrap_or(FixedOffset::east_opt(0)?))?;
+            Some((timestamp, name.to_string()))
+        })
     }
-    (v - old_min) / (old_max - old_min) * (new_max - new_min) + new_min
 }
 
-fn clamp(v: f64, min_v: f64, max_v: f64) -> f64 {
-    if v < min_v {
-        min_v
-    } else if v > max_v {
-        max_v
-    } else {
-        v
+/// A tag's value as text, however it was written.
+fn text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
     }
 }
 
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    let include_nulls = args.iter().any(|a| a == "--include-nulls");
-    let raw = args.iter().any(|a| a == "--raw");
+const HELP: &str = "\
+Usage: exif2csv [options] [image or directory ...]
 
-    println!("x,y,z");
+Reads images, or directories of them, or else exiftool -json output on
+stdin, and prints where and when each photo was taken as CSV: x,y,z scaled
+into -10 to 10 for a 3D viewer, or with --raw, lat,lon,date,alt,source.
 
-    let stdin = io::stdin();
-    let mut buffer = String::new();
-    let mut inside = false;
+Options:
+  --raw                  degrees, the capture date, metres above sea level,
+                         and which tags the date came from (source); source
+                         is only printed in raw mode
+  --include-nulls        with --raw, print records missing a position or
+                         date too, leaving those cells empty
+  --time year|day|epoch  what the time axis measures (default year)
+  --projection equirectangular|mercator|enu
+                         how latitude and longitude become east and north;
+                         enu is metres around the photos' centroid, so is
+                         always fitted to them
+  --axes east,time,north what x, y and z show; -north flips one
+  --fit                  scale each axis to the data instead of the whole
+                         world, -500 to 9000 m and 2010-2030; with enu, only
+                         time changes
+  -h, --help             print this
+";
 
-    for line in stdin.lock().lines() {
-        let line = line.unwrap();
-        if line.trim().is_empty() {
-            continue;
+fn main() {
+    let mut include_nulls = false;
+    let mut raw = false;
+    let mut options = project::Options::default();
+    // images, or directories of them; exiftool JSON on stdin if there are none
+    let mut paths: Vec<PathBuf> = Vec::new();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--include-nulls" => include_nulls = true,
+            "--raw" => raw = true,
+            "-h" | "--help" => {
+                print!("{HELP}");
+                return;
+            }
+            // year, day (of the year, with the time of day as a fraction) or epoch
+            "--time" => {
+                let name = expect_value(&arg, args.next());
+                options.time = project::Time::from_name(&name).unwrap_or_else(|| {
+                    usage(format!("--time expects year, day or epoch, got {name}"))
+                });
+            }
+            // equirectangular, mercator or enu (metres around the photos' centroid,
+            // always fitted to them)
+            "--projection" => {
+                let name = expect_value(&arg, args.next());
+                options.projection = project::Projection::from_name(&name).unwrap_or_else(|| {
+                    usage(format!(
+                        "--projection expects equirectangular, mercator or enu, got {name}"
+                    ))
+                });
+            }
+            // what x, y and z show, e.g. `east,time,north`; `-north` flips one
+            "--axes" => {
+                let axes = expect_value(&arg, args.next());
+                options.axes = project::parse_axes(&axes).unwrap_or_else(|e| usage(e));
+            }
+            // scale each axis to the data instead of the whole world, -500 to 9000 m
+            // and 2010-2030; only time is affected with enu
+            "--fit" => options.fit = true,
+            _ if arg.starts_with('-') => usage(format!("Unknown argument: {arg}")),
+            _ => paths.push(PathBuf::from(arg)),
         }
+    }
 
-        // Detect start of JSON object
-        if line.trim_start().starts_with('{') {
-            inside = true;
-            buffer.clear();
+    if raw {
+        println!("lat,lon,date,alt,source");
+    } else {
+        println!("x,y,z");
+    }
+    let mut output = Output {
+        include_nulls,
+        raw,
+        options,
+        points: Vec::new(),
+    };
+
+    if !paths.is_empty() {
+        let files = image::image_files(&paths).unwrap_or_else(|e| {
+            eprintln!("{e}");
+            process::exit(1);
+        });
+        // read in parallel, but print in file order
+        let records: Vec<io::Result<Record>> =
+            files.par_iter().map(|file| image::read(file)).collect();
+        for (file, record) in files.iter().zip(records) {
+            match record {
+                Ok(rec) => output.record(&rec),
+                Err(e) => eprintln!("{}: {e}", file.display()),
+            }
         }
+        output.finish();
+        return;
+    }
 
-        if inside {
-            buffer.push_str(&line);
-            buffer.push('\n');
+    let mut records = 0;
+    let mut unparseable = 0;
+    let read = json::read_records(io::stdin().lock(), |position, record| {
+        records += 1;
+        match record {
+            Ok(rec) => output.record(&rec),
+            Err(e) => {
+                unparseable += 1;
+                eprintln!("stdin: {position}: {e}");
+            }
         }
+    });
+    if let Err(e) = read {
+        eprintln!("stdin: {e}");
+        process::exit(1);
+    }
+    if unparseable > 0 {
+        eprintln!("{unparseable} of {records} records could not be parsed");
+    }
+    output.finish();
+}
+
+fn expect_value(arg: &str, value: Option<String>) -> String {
+    value.unwrap_or_else(|| usage(format!("{arg} expects a value")))
+}
+
+fn usage(message: String) -> ! {
+    eprintln!("{message}");
+    process::exit(2);
+}
+
+/// Prints each record as it's read, unless projecting needs all of them
+/// first, when it prints them at the end.
+struct Output {
+    include_nulls: bool,
+    raw: bool,
+    options: project::Options,
+    points: Vec<project::Point>,
+}
 
-        // Detect end of JSON object
-        if line.trim_end().ends_with('}') && inside {
-            inside = false;
-            if let Ok(rec) = serde_json::from_str::<Record>(&buffer) {
-                if !include_nulls
-                    && (rec.GPSLatitude.is_none()
-                        || rec.GPSLongitude.is_none()
-                        || rec.FileModifyDate.is_none())
-                {
-                    continue;
-                }
-
-                if let (Some(lat), Some(lon), Some(date_str)) =
-                    (rec.GPSLatitude, rec.GPSLongitude, rec.FileModifyDate)
-                {
-                    if raw {
-                        println!("{lat},{lon},{date_str}");
-                        continue;
-                    }
-
-                    if let Ok(dt) = DateTime::parse_from_str(&date_str, "%Y:%m:%d %H:%M:%S%:z") {
-                        let year = dt.year() as f64;
-
-                        // Normalize x (longitude), y (year/time), z (latitude)
-                        let x = normalize(lon, -180.0, 180.0, -10.0, 10.0);
-
-                        // Year → vertical axis (y)
-                        let clamped_year = clamp(year, 2010.0, 2030.0);
-                        let y = normalize(clamped_year, 2010.0, 2030.0, -10.0, 10.0);
-
-                        // Latitude → depth (z)
-                        let z = normalize(lat, -90.0, 90.0, -10.0, 10.0);
-
-                        println!("{x:.3},{y:.3},{z:.3}");
-                    }
-                }
+impl Output {
+    fn record(&mut self, rec: &Record) {
+        let (latitude, longitude) = (rec.latitude(), rec.longitude());
+        let timestamp = rec.timestamp();
+        if self.raw {
+            if !self.include_nulls
+                && (latitude.is_none() || longitude.is_none() || timestamp.is_none())
+            {
+                return;
             }
+            // new columns last, and empty if unknown, so existing ones stay put
+            let cell = |value: Option<f64>| value.map(|v| v.to_string()).unwrap_or_default();
+            let (date_str, source) = timestamp
+                .map(|(dt, source)| (dt.format("%Y:%m:%d %H:%M:%S%:z").to_string(), source))
+                .unwrap_or_default();
+            println!(
+                "{},{},{date_str},{},{source}",
+                cell(latitude),
+                cell(longitude),
+                cell(rec.altitude())
+            );
+            return;
+        }
+
+        // x, y and z need a position and a date
+        let (Some(lat), Some(lon), Some((dt, _))) = (latitude, longitude, timestamp) else {
+            return;
+        };
+
+        let point = project::Point {
+            latitude: lat,
+            longitude: lon,
+            altitude: rec.altitude(),
+            time: dt,
+        };
+        if self.options.needs_all_points() {
+            self.points.push(point);
+        } else {
+            print_xyz(project::project(&[point], &self.options)[0]);
+        }
+    }
+
+    fn finish(self) {
+        for xyz in project::project(&self.points, &self.options) {
+            print_xyz(xyz);
+        }
+    }
+}
+
+fn print_xyz([x, y, z]: [f64; 3]) {
+    println!("{x:.3},{y:.3},{z:.3}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn timestamp_sources_in_order() {
+        let cases: &[(Value, Option<(&str, &str)>)] = &[
+            // a capture time's own offset beats its OffsetTime tag, which
+            // beats the GPS position, which beats nothing (UTC)
+            (
+                json!({
+                    "DateTimeOriginal": "2021:03:04 10:11:12+09:00",
+                    "OffsetTimeOriginal": "-05:00",
+                    "GPSLongitude": -122.4,
+                }),
+                Some(("2021-03-04T10:11:12+09:00", "DateTimeOriginal")),
+            ),
+            (
+                json!({
+                    "DateTimeOriginal": "2021:03:04 10:11:12",
+                    "OffsetTimeOriginal": "-05:00",
+                    "GPSLongitude": 139.7,
+                }),
+                Some((
+                    "2021-03-04T10:11:12-05:00",
+                    "DateTimeOriginal+OffsetTimeOriginal",
+                )),
+            ),
+            (
+                json!({"DateTimeOriginal": "2021:03:04 10:11:12", "GPSLongitude": 139.7}),
+                Some(("2021-03-04T10:11:12+09:00", "DateTimeOriginal+GPS~nautical")),
+            ),
+            (
+                json!({"DateTimeOriginal": "2021:03:04 10:11:12"}),
+                Some(("2021-03-04T10:11:12+00:00", "DateTimeOriginal")),
+            ),
+            // DateTimeOriginal before CreateDate, which is used when the
+            // first doesn't parse, with its own OffsetTime tag
+            (
+                json!({
+                    "DateTimeOriginal": "2021:03:04 10:11:12",
+                    "CreateDate": "2022:01:01 00:00:00",
+                    "OffsetTimeDigitized": "+01:00",
+                }),
+                Some(("2021-03-04T10:11:12+00:00", "DateTimeOriginal")),
+            ),
+            (
+                json!({
+                    "DateTimeOriginal": "0000:00:00 00:00:00",
+                    "OffsetTimeOriginal": "+02:00",
+                    "CreateDate": "2022:01:01 00:00:00",
+                    "OffsetTimeDigitized": "+01:00",
+                }),
+                Some((
+                    "2022-01-01T00:00:00+01:00",
+                    "CreateDate+OffsetTimeDigitized",
+                )),
+            ),
+            // then the GPS clock, in UTC even with a longitude
+            (
+                json!({
+                    "GPSDateStamp": "2021:03:04",
+                    "GPSTimeStamp": "10 11 12",
+                    "GPSLongitude": 139.7,
+                    "FileCreateDate": "2023:05:06 07:08:09+02:00",
+                }),
+                Some(("2021-03-04T10:11:12+00:00", "GPSDateStamp+GPSTimeStamp")),
+            ),
+            // a GPS time without its date is no use
+            (
+                json!({
+                    "GPSTimeStamp": "10:11:12",
+                    "FileModifyDate": "2023:05:06 07:08:09+02:00",
+                }),
+                Some(("2023-05-06T07:08:09+02:00", "FileModifyDate")),
+            ),
+            // the file's dates last, creation first
+            (
+                json!({
+                    "FileCreateDate": "2023:05:06 07:08:09+02:00",
+                    "FileModifyDate": "2024:01:01 00:00:00+02:00",
+                }),
+                Some(("2023-05-06T07:08:09+02:00", "FileCreateDate")),
+            ),
+            (json!({"DateTimeOriginal": "unknown"}), None),
+            (json!({}), None),
+        ];
+        for (record, expected) in cases {
+            let rec: Record = serde_json::from_value(record.clone()).unwrap();
+            let actual = rec.timestamp();
+            let actual = actual
+                .as_ref()
+                .map(|(dt, source)| (dt.to_rfc3339(), source.as_str()));
+            let expected = expected.map(|(dt, source)| (dt.to_string(), source));
+            assert_eq!(actual, expected, "{record}");
         }
     }
 }
@@ -0,0 +1,511 @@
+use chrono::{DateTime, Datelike, FixedOffset, TimeZone, Timelike, Utc};
+
+// Every axis is scaled into this range
+const OUT_MIN: f64 = -10.0;
+const OUT_MAX: f64 = 10.0;
+
+// The years the time axis spans unless fitted to the data
+const FIRST_YEAR: i32 = 2010;
+const LAST_YEAR: i32 = 2030;
+
+// The altitudes the up axis spans unless fitted, from the Dead Sea shore
+// to above Everest, in metres
+const LOWEST: f64 = -500.0;
+const HIGHEST: f64 = 9000.0;
+
+// Web Mercator's extent in metres, and the latitude it's cut off at
+const MERCATOR_EXTENT: f64 = 20_037_508.342_789_244;
+const MERCATOR_MAX_LATITUDE: f64 = 85.051_128_779_806_59;
+
+// WGS84
+const EQUATORIAL_RADIUS: f64 = 6_378_137.0;
+const FLATTENING: f64 = 1.0 / 298.257_223_563;
+
+/// Where and when a photo was taken.
+pub struct Point {
+    pub latitude: f64,
+    pub longitude: f64,
+    /// Metres above sea level
+    pub altitude: Option<f64>,
+    pub time: DateTime<FixedOffset>,
+}
+
+/// What the time axis measures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Time {
+    /// The calendar year
+    Year,
+    /// The day of the year, with the time of day as a fraction, to show
+    /// seasons
+    Day,
+    /// Seconds since 1970, for a continuous timeline
+    Epoch,
+}
+
+impl Time {
+    pub fn from_name(name: &str) -> Option<Time> {
+        match name {
+            "year" => Some(Time::Year),
+            "day" => Some(Time::Day),
+            "epoch" => Some(Time::Epoch),
+            _ => None,
+        }
+    }
+
+    fn of(self, time: &DateTime<FixedOffset>) -> f64 {
+        match self {
+            Time::Year => time.year() as f64,
+            Time::Day => time.ordinal() as f64 + time.num_seconds_from_midnight() as f64 / 86400.0,
+            Time::Epoch => time.timestamp() as f64 + time.timestamp_subsec_nanos() as f64 / 1e9,
+        }
+    }
+
+    /// The range shown unless fitted, outside which times are clamped.
+    fn range(self) -> (f64, f64) {
+        let epoch = |year| {
+            Utc.with_ymd_and_hms(year, 1, 1, 0, 0, 0)
+                .single()
+                .expect("a valid date")
+                .timestamp() as f64
+        };
+        match self {
+            Time::Year => (FIRST_YEAR as f64, LAST_YEAR as f64),
+            Time::Day => (1.0, 367.0),
+            Time::Epoch => (epoch(FIRST_YEAR), epoch(LAST_YEAR)),
+        }
+    }
+}
+
+/// How latitude and longitude become east and north.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Projection {
+    /// Degrees as they are
+    Equirectangular,
+    /// Metres, as web maps use
+    Mercator,
+    /// Metres east, north and up from the centroid of all the photos, for
+    /// photos taken close together. Being local, these are always scaled to
+    /// the photos, with or without `fit`.
+    Enu,
+}
+
+impl Projection {
+    pub fn from_name(name: &str) -> Option<Projection> {
+        match name {
+            "equirectangular" => Some(Projection::Equirectangular),
+            "mercator" => Some(Projection::Mercator),
+            "enu" => Some(Projection::Enu),
+            _ => None,
+        }
+    }
+}
+
+/// A quantity shown on an axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    East,
+    North,
+    /// Altitude, or height above the centroid with `enu`
+    Up,
+    Time,
+}
+
+/// What one of the x, y and z columns shows, negated if `flipped`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Axis {
+    pub quantity: Quantity,
+    pub flipped: bool,
+}
+
+/// The x, y and z axes from e.g. `east,time,north` or `east,-north,up`,
+/// where `-` flips an axis.
+pub fn parse_axes(text: &str) -> Result<[Axis; 3], String> {
+    let axes = text
+        .split(',')
+        .map(|name| {
+            let name = name.trim();
+            let (flipped, name) = match name.strip_prefix('-') {
+                Some(name) => (true, name),
+                None => (false, name),
+            };
+            let quantity = match name {
+                "east" => Quantity::East,
+                "north" => Quantity::North,
+                "up" => Quantity::Up,
+                "time" => Quantity::Time,
+                _ => {
+                    return Err(format!(
+                        "unknown axis `{name}`, expected east, north, up or time"
+                    ))
+                }
+            };
+            Ok(Axis { quantity, flipped })
+        })
+        .collect::<Result<Vec<Axis>, String>>()?;
+    let axes: [Axis; 3] = axes
+        .try_into()
+        .map_err(|_| format!("expected three axes for x, y and z, got `{text}`"))?;
+    if (0..3).any(|i| (0..i).any(|j| axes[i].quantity == axes[j].quantity)) {
+        return Err(format!("axes repeat a quantity: `{text}`"));
+    }
+    Ok(axes)
+}
+
+/// How to turn points into x, y and z.
+pub struct Options {
+    pub time: Time,
+    pub projection: Projection,
+    pub axes: [Axis; 3],
+    /// Scale each axis to the data rather than to the whole world,
+    /// `LOWEST` to `HIGHEST` and `FIRST_YEAR` to `LAST_YEAR`. Only time is
+    /// affected with `Enu`, which is always fitted.
+    pub fit: bool,
+}
+
+impl Default for Options {
+    /// Longitude across, years up, latitude in depth, as always.
+    fn default() -> Options {
+        let axis = |quantity| Axis {
+            quantity,
+            flipped: false,
+        };
+        Options {
+            time: Time::Year,
+            projection: Projection::Equirectangular,
+            axes: [
+                axis(Quantity::East),
+                axis(Quantity::Time),
+                axis(Quantity::North),
+            ],
+            fit: false,
+        }
+    }
+}
+
+impl Options {
+    /// Whether projecting needs every point first, rather than one at a
+    /// time.
+    pub fn needs_all_points(&self) -> bool {
+        self.fit || self.projection == Projection::Enu
+    }
+}
+
+/// Each point's x, y and z, scaled into -10 to 10.
+pub fn project(points: &[Point], options: &Options) -> Vec<[f64; 3]> {
+    //
+    // East, north, up and time for every point, in the projection's units
+    //
+    let values: Vec<[f64; 4]> = match options.projection {
+        Projection::Equirectangular => points
+            .iter()
+            .map(|p| {
+                [
+                    p.longitude,
+                    p.latitude,
+                    p.altitude.unwrap_or(0.0),
+                    options.time.of(&p.time),
+                ]
+            })
+            .collect(),
+        Projection::Mercator => points
+            .iter()
+            .map(|p| {
+                let (east, north) = mercator(p.latitude, p.longitude);
+                [
+                    east,
+                    north,
+                    p.altitude.unwrap_or(0.0),
+                    options.time.of(&p.time),
+                ]
+            })
+            .collect(),
+        Projection::Enu => {
+            let ecef: Vec<[f64; 3]> = points
+                .iter()
+                .map(|p| ecef(p.latitude, p.longitude, p.altitude.unwrap_or(0.0)))
+                .collect();
+            let centroid = centroid(&ecef);
+            ecef.iter()
+                .zip(points)
+                .map(|(position, p)| {
+                    let [east, north, up] = enu(*position, centroid);
+                    [east, north, up, options.time.of(&p.time)]
+                })
+                .collect()
+        }
+    };
+
+    //
+    // The range of each, either the whole world or fitted to the data.
+    // East and north share a scale when fitted so the map keeps its shape.
+    // ENU is relative to the photos, so has no whole-world range.
+    //
+    let fitted = |index: usize| {
+        let (min, max) = values.iter().fold((f64::MAX, f64::MIN), |(min, max), v| {
+            (min.min(v[index]), max.max(v[index]))
+        });
+        if values.is_empty() {
+            (0.0, 1.0)
+        } else if min == max {
+            (min - 0.5, max + 0.5)
+        } else {
+            (min, max)
+        }
+    };
+    let world = match options.projection {
+        Projection::Equirectangular if !options.fit => Some(((-180.0, 180.0), (-90.0, 90.0))),
+        Projection::Mercator if !options.fit => Some((
+            (-MERCATOR_EXTENT, MERCATOR_EXTENT),
+            (-MERCATOR_EXTENT, MERCATOR_EXTENT),
+        )),
+        _ => None,
+    };
+    let (east_range, north_range) = world.unwrap_or_else(|| same_scale(fitted(0), fitted(1)));
+    let up_range = if options.fit || options.projection == Projection::Enu {
+        fitted(2)
+    } else {
+        (LOWEST, HIGHEST)
+    };
+    let time_range = if options.fit {
+        fitted(3)
+    } else {
+        options.time.range()
+    };
+    let ranges = [east_range, north_range, up_range, time_range];
+
+    values
+        .iter()
+        .map(|value| {
+            options.axes.map(|axis| {
+                let index = match axis.quantity {
+                    Quantity::East => 0,
+                    Quantity::North => 1,
+                    Quantity::Up => 2,
+                    Quantity::Time => 3,
+                };
+                let (min, max) = ranges[index];
+                let scaled = normalize(clamp(value[index], min, max), min, max, OUT_MIN, OUT_MAX);
+                if axis.flipped {
+                    -scaled
+                } else {
+                    scaled
+                }
+            })
+        })
+        .collect()
+}
+
+/// Two ranges widened about their middles to the larger span.
+fn same_scale(a: (f64, f64), b: (f64, f64)) -> ((f64, f64), (f64, f64)) {
+    let half = (a.1 - a.0).max(b.1 - b.0) / 2.0;
+    let around = |(min, max): (f64, f64)| {
+        let middle = (min + max) / 2.0;
+        (middle - half, middle + half)
+    };
+    (around(a), around(b))
+}
+
+fn normalize(v: f64, old_min: f64, old_max: f64, new_min: f64, new_max: f64) -> f64 {
+    if old_max == old_min {
+        return new_min;
+    }
+    (v - old_min) / (old_max - old_min) * (new_max - new_min) + new_min
+}
+
+fn clamp(v: f64, min_v: f64, max_v: f64) -> f64 {
+    if v < min_v {
+        min_v
+    } else if v > max_v {
+        max_v
+    } else {
+        v
+    }
+}
+
+//------------------------------------------------------------------------------
+// Projections
+//------------------------------------------------------------------------------
+
+/// Web Mercator metres east and north, cut off near the poles.
+fn mercator(latitude: f64, longitude: f64) -> (f64, f64) {
+    let latitude = latitude
+        .clamp(-MERCATOR_MAX_LATITUDE, MERCATOR_MAX_LATITUDE)
+        .to_radians();
+    let east = EQUATORIAL_RADIUS * longitude.to_radians();
+    let north = EQUATORIAL_RADIUS * (std::f64::consts::FRAC_PI_4 + latitude / 2.0).tan().ln();
+    (east, north)
+}
+
+/// Earth-centred, Earth-fixed metres.
+fn ecef(latitude: f64, longitude: f64, altitude: f64) -> [f64; 3] {
+    let e2 = FLATTENING * (2.0 - FLATTENING);
+    let (lat, lon) = (latitude.to_radians(), longitude.to_radians());
+    let n = EQUATORIAL_RADIUS / (1.0 - e2 * lat.sin().powi(2)).sqrt();
+    [
+        (n + altitude) * lat.cos() * lon.cos(),
+        (n + altitude) * lat.cos() * lon.sin(),
+        (n * (1.0 - e2) + altitude) * lat.sin(),
+    ]
+}
+
+fn centroid(positions: &[[f64; 3]]) -> [f64; 3] {
+    let count = positions.len().max(1) as f64;
+    let sum = positions.iter().fold([0.0; 3], |sum, p| {
+        [sum[0] + p[0], sum[1] + p[1], sum[2] + p[2]]
+    });
+    sum.map(|total| total / count)
+}
+
+/// `position` in metres east, north and up of `origin`, along the ground
+/// there.
+fn enu(position: [f64; 3], origin: [f64; 3]) -> [f64; 3] {
+    let e2 = FLATTENING * (2.0 - FLATTENING);
+    let [x, y, z] = origin;
+    let lon = y.atan2(x);
+    // geodetic latitude of the origin, near enough for a local frame
+    let lat = z.atan2((x * x + y * y).sqrt() * (1.0 - e2));
+    let [dx, dy, dz] = [position[0] - x, position[1] - y, position[2] - z];
+    [
+        -lon.sin() * dx + lon.cos() * dy,
+        -lat.sin() * lon.cos() * dx - lat.sin() * lon.sin() * dy + lat.cos() * dz,
+        lat.cos() * lon.cos() * dx + lat.cos() * lon.sin() * dy + lat.sin() * dz,
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn point(latitude: f64, longitude: f64, altitude: Option<f64>, time: &str) -> Point {
+        Point {
+            latitude,
+            longitude,
+            altitude,
+            time: DateTime::parse_from_rfc3339(time).expect("an RFC 3339 time"),
+        }
+    }
+
+    fn options(axes: &str, fit: bool) -> Options {
+        Options {
+            axes: parse_axes(axes).expect("valid axes"),
+            fit,
+            ..Options::default()
+        }
+    }
+
+    fn assert_near(actual: &[[f64; 3]], expected: &[[f64; 3]]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!(
+                a.iter().zip(e).all(|(a, e)| (a - e).abs() < 1e-6),
+                "{actual:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn defaults_match_the_original_output() {
+        // longitude across -180..180, the year up 2010..2030 and latitude
+        // in depth -90..90, all into -10..10
+        let points = [
+            point(45.0, 90.0, None, "2020-06-01T12:00:00Z"),
+            point(-90.0, -180.0, Some(100.0), "2010-01-01T00:00:00Z"),
+            point(0.0, 0.0, None, "2035-01-01T00:00:00+09:00"),
+        ];
+        assert_near(
+            &project(&points, &Options::default()),
+            &[[5.0, 0.0, 5.0], [-10.0, -10.0, -10.0], [0.0, 10.0, 0.0]],
+        );
+    }
+
+    #[test]
+    fn unfitted_ranges_clamp() {
+        // altitude -500..9000 m, time 2010..2030 however it's measured
+        let points = [
+            point(0.0, 0.0, Some(9500.0), "2040-01-01T00:00:00Z"),
+            point(0.0, 0.0, Some(-1000.0), "2000-01-01T00:00:00Z"),
+            point(0.0, 0.0, Some(4250.0), "2020-01-01T00:00:00Z"),
+        ];
+        assert_near(
+            &project(&points, &options("up,time,north", false)),
+            &[[10.0, 10.0, 0.0], [-10.0, -10.0, 0.0], [0.0, 0.0, 0.0]],
+        );
+        // 2020 isn't quite halfway through the seconds, so just the ends
+        let epoch = Options {
+            time: Time::Epoch,
+            ..options("up,time,north", false)
+        };
+        assert_near(
+            &project(&points[..2], &epoch),
+            &[[10.0, 10.0, 0.0], [-10.0, -10.0, 0.0]],
+        );
+    }
+
+    #[test]
+    fn fit_keeps_east_and_north_on_one_scale() {
+        // 4° wide and 1° tall, so north only spans a quarter of the range
+        let points = [
+            point(0.0, 0.0, None, "2020-01-01T00:00:00Z"),
+            point(1.0, 4.0, None, "2022-01-01T00:00:00Z"),
+        ];
+        assert_near(
+            &project(&points, &options("east,time,north", true)),
+            &[[-10.0, -10.0, -2.5], [10.0, 10.0, 2.5]],
+        );
+        // and the same tall rather than wide
+        let points = [
+            point(0.0, 0.0, None, "2020-01-01T00:00:00Z"),
+            point(4.0, 1.0, None, "2020-01-01T00:00:00Z"),
+        ];
+        assert_near(
+            &project(&points, &options("east,north,time", true)),
+            &[[-2.5, -10.0, 0.0], [2.5, 10.0, 0.0]],
+        );
+    }
+
+    #[test]
+    fn enu_is_around_the_centroid() {
+        // a cross of points 0.001° from the origin, which is their centroid
+        let points = [
+            point(0.0, 0.001, None, "2020-01-01T00:00:00Z"),
+            point(0.0, -0.001, None, "2020-01-01T00:00:00Z"),
+            point(0.001, 0.0, None, "2020-01-01T00:00:00Z"),
+            point(-0.001, 0.0, None, "2020-01-01T00:00:00Z"),
+        ];
+        let enu = Options {
+            projection: Projection::Enu,
+            ..options("east,north,time", false)
+        };
+        let xyz = project(&points, &enu);
+        // east and west span the shared range; a degree of latitude is a
+        // little shorter than one of longitude at the equator
+        assert!((xyz[0][0] - 10.0).abs() < 1e-6 && xyz[0][1].abs() < 1e-3);
+        assert!((xyz[1][0] + 10.0).abs() < 1e-6 && xyz[1][1].abs() < 1e-3);
+        assert!(xyz[2][0].abs() < 1e-3 && xyz[2][1] > 9.9 && xyz[2][1] < 10.0);
+        assert!(xyz[3][0].abs() < 1e-3 && xyz[3][1] < -9.9 && xyz[3][1] > -10.0);
+    }
+
+    #[test]
+    fn axes() {
+        let axis = |quantity, flipped| Axis { quantity, flipped };
+        assert_eq!(parse_axes("east,time,north"), Ok(Options::default().axes));
+        assert_eq!(
+            parse_axes(" east , -north,up"),
+            Ok([
+                axis(Quantity::East, false),
+                axis(Quantity::North, true),
+                axis(Quantity::Up, false),
+            ])
+        );
+        for bad in [
+            "east,east,up",
+            "east,-east,up",
+            "east,north",
+            "east,north,up,time",
+            "east,south,up",
+            "",
+        ] {
+            assert!(parse_axes(bad).is_err(), "{bad:?}");
+        }
+    }
+}
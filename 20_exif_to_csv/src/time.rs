@@ -0,0 +1,215 @@
+use chrono::{DateTime, FixedOffset, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+
+/// A date and time as EXIF writes it, `2021:03:04 10:11:12`, or as XMP
+/// does, `2021-03-04T10:11:12`, either with optional fractional seconds and
+/// an optional offset (`-08:00`, `+0900` or `Z`), which is returned if
+/// present.
+pub fn parse(text: &str) -> Option<(NaiveDateTime, Option<FixedOffset>)> {
+    let text = text.trim();
+    let (date, time) = text.split_once([' ', 'T']).unwrap_or((text, "00:00:00"));
+    let date = NaiveDate::parse_from_str(&date.replace(':', "-"), "%Y-%m-%d").ok()?;
+
+    // the offset starts at the first sign or `Z` after the time
+    let (time, zone) = match time.find(['+', '-', 'Z']) {
+        Some(at) => (&time[..at], Some(offset(&time[at..])?)),
+        None => (time, None),
+    };
+    Some((date.and_time(clock(time)?), zone))
+}
+
+/// `H:M:S`, with optional fractional seconds, or as exiftool's `-n` gives
+/// `GPSTimeStamp`, `H M S`.
+fn clock(text: &str) -> Option<NaiveTime> {
+    let mut parts = text
+        .trim()
+        .split([':', ' '])
+        .filter(|part| !part.is_empty());
+    let hours: u32 = parts.next()?.parse().ok()?;
+    let minutes: u32 = parts.next()?.parse().ok()?;
+    let seconds: f64 = parts.next().map_or(Ok(0.0), str::parse).ok()?;
+    if parts.next().is_some() || !(0.0..61.0).contains(&seconds) {
+        return None;
+    }
+    // rounded, or `.345` would come out a nanosecond short
+    let nanos = (seconds.fract() * 1e9).round().min(999_999_999.0) as u32;
+    NaiveTime::from_hms_nano_opt(hours, minutes, seconds.trunc() as u32, nanos)
+}
+
+/// `+09:00`, `-0800`, `+09` or `Z`, as in `OffsetTimeOriginal`.
+pub fn offset(text: &str) -> Option<FixedOffset> {
+    let text = text.trim();
+    if text == "Z" {
+        return FixedOffset::east_opt(0);
+    }
+    let sign = match text.chars().next()? {
+        '+' => 1,
+        '-' => -1,
+        _ => return None,
+    };
+    let digits: String = text[1..].chars().filter(|c| *c != ':').collect();
+    let (hours, minutes) = match digits.len() {
+        2 => (digits.parse::<i32>().ok()?, 0),
+        4 => (
+            digits[..2].parse::<i32>().ok()?,
+            digits[2..].parse::<i32>().ok()?,
+        ),
+        _ => return None,
+    };
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60))
+}
+
+/// `GPSDateStamp` and `GPSTimeStamp`, which are always UTC.
+pub fn gps(date: &str, time: &str) -> Option<DateTime<FixedOffset>> {
+    let date = NaiveDate::parse_from_str(&date.trim().replace(':', "-"), "%Y-%m-%d").ok()?;
+    let utc = Utc.from_utc_datetime(&date.and_time(clock(time)?));
+    Some(utc.fixed_offset())
+}
+
+/// The time zone at `longitude`, approximated as the nautical zone: whole
+/// hours, 15° wide, centred on the Greenwich meridian. Civil zones follow
+/// borders and daylight saving, so this can be an hour or two out, which
+/// is why a time placed with it says so in its source column. It's only
+/// used for photos that recorded no offset, and without a time zone
+/// database to hand it keeps times on the right side of midnight far more
+/// often than assuming UTC would.
+pub fn nautical_zone(longitude: f64) -> FixedOffset {
+    let hours = (longitude / 15.0).round().clamp(-12.0, 12.0) as i32;
+    FixedOffset::east_opt(hours * 3600).expect("within a day")
+}
+
+/// `local` in `zone`, if it's representable.
+pub fn at(local: NaiveDateTime, zone: FixedOffset) -> Option<DateTime<FixedOffset>> {
+    zone.from_local_datetime(&local).single()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The local time, as `%Y-%m-%d %H:%M:%S%.f`, and the offset in seconds.
+    type Parsed = (&'static str, Option<i32>);
+
+    #[test]
+    fn dates_and_times() {
+        let cases: &[(&str, Option<Parsed>)] = &[
+            // EXIF and XMP forms
+            ("2021:03:04 10:11:12", Some(("2021-03-04 10:11:12", None))),
+            ("2021-03-04T10:11:12", Some(("2021-03-04 10:11:12", None))),
+            (" 2021:03:04 10:11:12 ", Some(("2021-03-04 10:11:12", None))),
+            // no seconds, or no time at all
+            ("2021-03-04T10:11", Some(("2021-03-04 10:11:00", None))),
+            ("2021:03:04", Some(("2021-03-04 00:00:00", None))),
+            // fractional seconds
+            (
+                "2021:03:04 10:11:12.345",
+                Some(("2021-03-04 10:11:12.345", None)),
+            ),
+            // offsets
+            (
+                "2021-03-04T10:11:12Z",
+                Some(("2021-03-04 10:11:12", Some(0))),
+            ),
+            (
+                "2021:03:04 10:11:12+0900",
+                Some(("2021-03-04 10:11:12", Some(9 * 3600))),
+            ),
+            (
+                "2021-03-04T10:11:12.5-08:00",
+                Some(("2021-03-04 10:11:12.500", Some(-8 * 3600))),
+            ),
+            // exiftool's placeholder for an unknown date, impossible dates
+            // and times, stray parts and bad offsets
+            ("0000:00:00 00:00:00", None),
+            ("2021:02:30 10:11:12", None),
+            ("2021:03:04 25:11:12", None),
+            ("2021:03:04 10:11:61", None),
+            ("2021:03:04 10:11:12 13", None),
+            ("2021:03:04 10:11:12+9", None),
+            ("2021:03:04 10:11:12+09:00:00", None),
+            ("yesterday", None),
+            ("", None),
+        ];
+        for &(input, expected) in cases {
+            let actual = parse(input).map(|(local, zone)| {
+                (
+                    local.format("%Y-%m-%d %H:%M:%S%.f").to_string(),
+                    zone.map(|zone| zone.local_minus_utc()),
+                )
+            });
+            let expected = expected.map(|(local, zone)| (local.to_string(), zone));
+            assert_eq!(actual, expected, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn offsets() {
+        let cases: &[(&str, Option<i32>)] = &[
+            ("+09:00", Some(9 * 3600)),
+            ("-0800", Some(-8 * 3600)),
+            ("+09", Some(9 * 3600)),
+            (" +05:30 ", Some(5 * 3600 + 30 * 60)),
+            ("Z", Some(0)),
+            ("+00:00", Some(0)),
+            ("09:00", None),
+            ("+9", None),
+            ("+123", None),
+            ("+ab:cd", None),
+            ("+25:00", None),
+            ("", None),
+        ];
+        for &(input, expected) in cases {
+            assert_eq!(
+                offset(input).map(|zone| zone.local_minus_utc()),
+                expected,
+                "{input:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn gps_stamps() {
+        let cases: &[(&str, &str, Option<&str>)] = &[
+            ("2021:03:04", "10:11:12", Some("2021-03-04T10:11:12+00:00")),
+            ("2021-03-04", "10:11:12", Some("2021-03-04T10:11:12+00:00")),
+            // exiftool's `-n` form
+            (
+                "2021:03:04",
+                "10 11 12.5",
+                Some("2021-03-04T10:11:12.500+00:00"),
+            ),
+            ("2021:03:04", "10:11", Some("2021-03-04T10:11:00+00:00")),
+            ("2021:13:04", "10:11:12", None),
+            ("2021:03:04", "24:00:00", None),
+            ("2021:03:04", "10", None),
+            ("", "10:11:12", None),
+        ];
+        for &(date, time, expected) in cases {
+            assert_eq!(
+                gps(date, time).map(|t| t.to_rfc3339()).as_deref(),
+                expected,
+                "{date:?} {time:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn nautical_zones() {
+        let cases: &[(f64, i32)] = &[
+            (0.0, 0),
+            (7.4, 0),
+            (7.6, 1),
+            (-0.1, 0),
+            (139.7, 9),
+            (-122.4, -8),
+            (180.0, 12),
+            (-180.0, -12),
+        ];
+        for &(longitude, hours) in cases {
+            assert_eq!(
+                nautical_zone(longitude).local_minus_utc(),
+                hours * 3600,
+                "{longitude}"
+            );
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+const START: &str = "<x:xmpmeta";
+const END: &str = "</x:xmpmeta>";
+
+// A packet longer than this is taken to be missing its end
+const MAX_PACKET: usize = 4 << 20;
+const CHUNK: usize = 64 << 10;
+
+/// The XMP for an image: from a sidecar beside it, as RAW workflows write
+/// (`IMG_1234.xmp` or `IMG_1234.CR2.xmp`), or else the packet embedded in
+/// the file itself.
+pub fn read(image: &Path) -> io::Result<Option<String>> {
+    let mut with_suffix = image.as_os_str().to_owned();
+    with_suffix.push(".xmp");
+    for sidecar in [image.with_extension("xmp"), PathBuf::from(with_suffix)] {
+        if sidecar.is_file() {
+            return packet(File::open(sidecar)?);
+        }
+    }
+    packet(File::open(image)?)
+}
+
+/// The first `<x:xmpmeta>` element in `reader`, read a chunk at a time so
+/// only the packet, and not the whole image, is held in memory.
+fn packet(mut reader: impl Read) -> io::Result<Option<String>> {
+    // from the start tag on once it's found; until then, only enough of
+    // the last chunk to catch a tag split across two
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut started = false;
+    let mut chunk = vec![0; CHUNK];
+    loop {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            return Ok(None);
+        }
+        // an end tag may begin in what was already searched
+        let from = buffer.len().saturating_sub(END.len() - 1);
+        buffer.extend_from_slice(&chunk[..read]);
+        let from = if started {
+            from
+        } else {
+            let Some(at) = find(&buffer, START.as_bytes()) else {
+                buffer.drain(..buffer.len().saturating_sub(START.len() - 1));
+                continue;
+            };
+            buffer.drain(..at);
+            started = true;
+            0
+        };
+        if let Some(end) = find(&buffer[from..], END.as_bytes()) {
+            let end = from + end + END.len();
+            return Ok(Some(String::from_utf8_lossy(&buffer[..end]).into_owned()));
+        }
+        if buffer.len() > MAX_PACKET {
+            return Ok(None);
+        }
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// A simple property such as `exif:GPSLatitude`, written either as an
+/// attribute or as an element.
+pub fn property<'a>(packet: &'a str, name: &str) -> Option<&'a str> {
+    for quote in ['"', '\''] {
+        let attribute = format!("{name}={quote}");
+        if let Some(at) = packet.find(&attribute) {
+            let value = &packet[at + attribute.len()..];
+            return value.find(quote).map(|end| &value[..end]);
+        }
+    }
+    let open = format!("<{name}>");
+    let at = packet.find(&open)? + open.len();
+    let value = &packet[at..];
+    value.find('<').map(|end| value[..end].trim())
+}
@@ -0,0 +1,39 @@
+// With the `antlr` feature, builds from the checked-in generated lexer, so
+// neither Java nor the ANTLR tool (nor the network to fetch them) is
+// needed. `make antlr` regenerates it after editing the grammar, and
+// records the grammar's hash in its header.
+const GENERATED: &str = "src/antlr/grammars/cronlexer.rs";
+const GRAMMAR: &str = "grammars/CronLexer.g4";
+
+fn main() {
+    println!("cargo:rerun-if-changed={GENERATED}");
+    println!("cargo:rerun-if-changed={GRAMMAR}");
+    #[cfg(feature = "antlr")]
+    check_generated();
+}
+
+/// Warn if the grammar has changed since the lexer was generated. File
+/// times say nothing after a clone or checkout, so this compares the
+/// grammar's hash with the one `make antlr` wrote into the lexer.
+#[cfg(feature = "antlr")]
+fn check_generated() {
+    use sha2::{Digest, Sha256};
+    use std::fs;
+
+    const HASH_PREFIX: &str = "// CronLexer.g4 sha256: ";
+
+    let Ok(generated) = fs::read_to_string(GENERATED) else {
+        panic!("{GENERATED} is missing, run `make antlr` to generate it from {GRAMMAR}");
+    };
+    let grammar = fs::read_to_string(GRAMMAR).unwrap_or_else(|e| panic!("{GRAMMAR}: {e}"));
+    // as checked out, whatever the line endings
+    let hash: String = Sha256::digest(grammar.replace("\r\n", "\n"))
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    match generated.lines().find_map(|line| line.strip_prefix(HASH_PREFIX)) {
+        Some(recorded) if recorded.trim() == hash => {}
+        Some(_) => println!("cargo:warning={GRAMMAR} has changed since {GENERATED} was generated, run `make antlr`"),
+        None => println!("cargo:warning={GENERATED} doesn't record which grammar it came from, run `make antlr`"),
+    }
+}
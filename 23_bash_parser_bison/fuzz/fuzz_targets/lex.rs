@@ -0,0 +1,25 @@
+#![no_main]
+
+// Spans must stay in bounds, on char boundaries and in order whatever the
+// input, and tokens must never contain whitespace.
+
+use croncheck::lexer::lex;
+use croncheck::schedule::Dialect;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|source: &str| {
+    for dialect in [Dialect::Vixie, Dialect::Quartz] {
+        let (tokens, errors) = lex(source, dialect);
+        let mut last_end = 0;
+        for (_, span) in &tokens {
+            assert!(last_end <= span.start && span.start < span.end && span.end <= source.len());
+            assert!(source.is_char_boundary(span.start) && source.is_char_boundary(span.end));
+            assert!(!source[span.clone()].contains(char::is_whitespace));
+            last_end = span.end;
+        }
+        for error in &errors {
+            assert!(error.span.end <= source.len());
+            assert!(source.is_char_boundary(error.span.start));
+        }
+    }
+});
@@ -0,0 +1,32 @@
+#![no_main]
+
+// Lexing, parsing and the next-run search must not panic in any dialect.
+
+use chrono::NaiveDate;
+use croncheck::crontab;
+use croncheck::lexer::lex;
+use croncheck::schedule::Dialect;
+use libfuzzer_sys::fuzz_target;
+
+const DIALECTS: [Dialect; 5] = [
+    Dialect::Vixie,
+    Dialect::Kubernetes,
+    Dialect::Jenkins,
+    Dialect::Quartz,
+    Dialect::Spring,
+];
+
+fuzz_target!(|source: &str| {
+    let start = NaiveDate::from_ymd_opt(2024, 2, 28)
+        .and_then(|d| d.and_hms_opt(23, 59, 59))
+        .expect("valid start");
+    for dialect in DIALECTS {
+        let (tokens, _) = lex(source, dialect);
+        let parsed = crontab::parse(source, &tokens, dialect);
+        for entry in &parsed.entries {
+            if let Some(next) = entry.schedule.next_after(start) {
+                assert!(next > start);
+            }
+        }
+    }
+});
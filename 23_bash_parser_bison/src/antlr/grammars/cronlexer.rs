@@ -1,4 +1,5 @@
 // Generated from grammars/CronLexer.g4 by ANTLR 4.8
+// CronLexer.g4 sha256: 85e7c617659f34bdaa9a2a459db1f05c0cd23a02b340797dfa7577fad355b623
 #![allow(dead_code)]
 #![allow(nonstandard_style)]
 #![allow(unused_imports)]
@@ -76,11 +77,12 @@ pub type LocalTokenFactory<'input> = CommonTokenFactory;
 
 type From<'a> = <LocalTokenFactory<'a> as TokenFactory<'a> >::From;
 
-#[derive(Tid)]
 pub struct CronLexer<'input, Input:CharStream<From<'input> >> {
 	base: BaseLexer<'input,CronLexerActions,Input,LocalTokenFactory<'input>>,
 }
 
+antlr_rust::tid! { impl<'input,Input> TidAble<'input> for CronLexer<'input,Input> where Input:CharStream<From<'input> > }
+
 impl<'input, Input:CharStream<From<'input> >> Deref for CronLexer<'input,Input>{
 	type Target = BaseLexer<'input,CronLexerActions,Input,LocalTokenFactory<'input>>;
 
@@ -113,7 +115,7 @@ impl<'input, Input:CharStream<From<'input> >> CronLexer<'input,Input>{
     }
 
 	pub fn new_with_token_factory(input: Input, tf: &'input LocalTokenFactory<'input>) -> Self {
-		antlr_rust::recognizer::check_version("0","2");
+		antlr_rust::recognizer::check_version("0","3");
     	Self {
 			base: BaseLexer::new_base_lexer(
 				input,
@@ -0,0 +1,190 @@
+//! The ANTLR `CronLexer`, kept as an independent oracle for croncheck's
+//! chumsky lexer: both lex the same crontab and every line on which their
+//! token streams disagree is reported.
+
+use antlr_rust::token::{Token as _, TOKEN_EOF};
+use antlr_rust::{InputStream, TokenSource};
+use crate::crontab::LineIndex;
+use crate::lexer::{self, Token};
+use crate::schedule::Dialect;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+// generated by `make antlr`, so left as ANTLR writes it
+#[allow(clippy::all)]
+mod grammars {
+    pub mod cronlexer;
+}
+
+use grammars::cronlexer::{_SYMBOLIC_NAMES, CronLexer};
+
+/// A token as its ANTLR token type name and source text.
+pub type Lexeme = (&'static str, String);
+
+/// A line the two lexers tokenized differently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Disagreement {
+    pub line: usize,
+    pub antlr: Vec<Lexeme>,
+    pub chumsky: Vec<Lexeme>,
+}
+
+impl fmt::Display for Disagreement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |lexemes: &[Lexeme]| {
+            lexemes
+                .iter()
+                .map(|(kind, text)| format!("{kind}({text})"))
+                .collect::<Vec<_>>()
+                .join(" ")
+        };
+        writeln!(f, "line {}:", self.line)?;
+        writeln!(f, "  antlr:   {}", show(&self.antlr))?;
+        write!(f, "  chumsky: {}", show(&self.chumsky))
+    }
+}
+
+//------------------------------------------------------------------------------
+// The two lexers
+//------------------------------------------------------------------------------
+
+/// Tokens from the generated ANTLR lexer, by 1-based line.
+pub fn antlr_tokens(source: &str) -> BTreeMap<usize, Vec<Lexeme>> {
+    let mut lexer = CronLexer::new(InputStream::new(source));
+    let mut lines: BTreeMap<usize, Vec<Lexeme>> = BTreeMap::new();
+    loop {
+        let token = lexer.next_token();
+        let token_type = token.get_token_type();
+        if token_type == TOKEN_EOF {
+            break;
+        }
+        let kind = _SYMBOLIC_NAMES
+            .get(token_type as usize)
+            .copied()
+            .flatten()
+            .unwrap_or("ERROR");
+        lines
+            .entry(token.get_line() as usize)
+            .or_default()
+            .push((kind, token.get_text().to_string()));
+    }
+    lines
+}
+
+/// Tokens from croncheck's chumsky lexer, by 1-based line, named with the
+/// ANTLR vocabulary so the two streams compare directly. A line the lexer
+/// rejects shows up as a single ERROR token.
+pub fn chumsky_tokens(source: &str) -> BTreeMap<usize, Vec<Lexeme>> {
+    let index = LineIndex::new(source);
+    let (tokens, errors) = lexer::lex(source, Dialect::Vixie);
+    let mut lines: BTreeMap<usize, Vec<Lexeme>> = BTreeMap::new();
+    for (token, span) in &tokens {
+        lines
+            .entry(index.line(span.start))
+            .or_default()
+            .push((antlr_kind(token), source[span.clone()].to_string()));
+    }
+    for error in errors {
+        lines
+            .entry(index.line(error.span.start))
+            .or_default()
+            .push(("ERROR", error.message));
+    }
+    lines
+}
+
+/// The grammar's vocabulary is coarser than croncheck's, so finer token
+/// kinds fold onto the ANTLR rule that would have matched them.
+fn antlr_kind(token: &Token) -> &'static str {
+    match token {
+        Token::Star => "STAR",
+        Token::Slash => "SLASH",
+        Token::Comma => "COMMA",
+        Token::Dash => "DASH",
+        Token::MonthName(_) => "MONTH_NAME",
+        Token::DowName(_) => "DOW_NAMEa",
+        Token::Int(_) => "INT",
+        Token::HttpUrl(_) | Token::SshUrl(_) | Token::Url(_) => "URL",
+        // the grammar lexes quoted strings as QUOTED_PATH
+        Token::Path(_) | Token::StringLiteral(_) => "PATH",
+        Token::CliOption(_) => "CLI_OPTION",
+        // PROGRAM is the grammar's catch-all for everything else
+        Token::Program(_)
+        | Token::Variable(_)
+        | Token::Equals
+        | Token::Redirect(_)
+        | Token::Async
+        | Token::NthWeekday(_) => "PROGRAM",
+    }
+}
+
+//------------------------------------------------------------------------------
+// Comparison
+//------------------------------------------------------------------------------
+
+/// Every line whose token streams differ, in line order.
+pub fn compare(source: &str) -> Vec<Disagreement> {
+    let mut antlr = antlr_tokens(source);
+    let mut chumsky = chumsky_tokens(source);
+    let mut lines: Vec<usize> = antlr.keys().chain(chumsky.keys()).copied().collect();
+    lines.sort_unstable();
+    lines.dedup();
+
+    lines
+        .into_iter()
+        .filter_map(|line| {
+            let antlr = antlr.remove(&line).unwrap_or_default();
+            let chumsky = chumsky.remove(&line).unwrap_or_default();
+            (antlr != chumsky).then_some(Disagreement {
+                line,
+                antlr,
+                chumsky,
+            })
+        })
+        .collect()
+}
+
+/// The crontabs to compare: files as given, directories expanded to the
+/// `.crontab` files directly inside them, sorted so reports are stable.
+pub fn corpus_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut entries: Vec<PathBuf> = fs::read_dir(path)?
+                .map(|entry| entry.map(|e| e.path()))
+                .collect::<io::Result<_>>()?;
+            // skip the golden `.tokens` files that sit next to them
+            entries.retain(|p| p.is_file() && p.extension().is_some_and(|ext| ext == "crontab"));
+            entries.sort();
+            files.extend(entries);
+        } else {
+            files.push(path.clone());
+        }
+    }
+    Ok(files)
+}
+
+/// The disagreement report for a set of crontabs, as `make antlr-diff` prints
+/// it and the differential test checks it.
+pub fn report(files: &[PathBuf]) -> io::Result<(String, usize)> {
+    let mut out = String::new();
+    let mut count = 0;
+    for file in files {
+        let source = fs::read_to_string(file)?;
+        for disagreement in compare(&source) {
+            out.push_str(&format!("{}: {disagreement}\n", display_name(file)));
+            count += 1;
+        }
+    }
+    Ok((out, count))
+}
+
+// corpus paths are reported by file name so the report doesn't depend on
+// where it was run from
+fn display_name(file: &Path) -> String {
+    file.file_name()
+        .map_or_else(|| file.display().to_string(), |name| name.to_string_lossy().into_owned())
+}
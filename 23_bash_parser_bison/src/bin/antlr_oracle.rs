@@ -0,0 +1,54 @@
+use colored::Colorize;
+use croncheck::antlr::{antlr_tokens, corpus_files, report};
+use std::env;
+use std::io::{self, Read};
+use std::path::PathBuf;
+
+//------------------------------------------------------------------------------
+// Main program
+//------------------------------------------------------------------------------
+
+// croncheck-antlr-oracle FILE|DIR...   report where the lexers disagree
+// croncheck-antlr-oracle < crontab    dump the ANTLR token stream
+fn main() {
+    let paths: Vec<PathBuf> = env::args().skip(1).map(PathBuf::from).collect();
+    if paths.is_empty() {
+        dump_stdin();
+        return;
+    }
+
+    let files = corpus_files(&paths).unwrap_or_else(|e| {
+        eprintln!("Failed to list corpus: {e}");
+        std::process::exit(2);
+    });
+    let (text, count) = report(&files).unwrap_or_else(|e| {
+        eprintln!("Failed to read corpus: {e}");
+        std::process::exit(2);
+    });
+    print!("{text}");
+    eprintln!("{count} disagreement(s) in {} file(s)", files.len());
+    if count > 0 {
+        std::process::exit(1);
+    }
+}
+
+fn dump_stdin() {
+    let mut input = String::new();
+    if let Err(e) = io::stdin().read_to_string(&mut input) {
+        eprintln!("Failed to read stdin: {e}");
+        std::process::exit(1);
+    }
+
+    for (line, tokens) in antlr_tokens(&input) {
+        for (kind, text) in tokens {
+            let tt_label = format!("[{kind}]").bright_magenta().bold();
+            eprintln!(
+                "{:<7} {:>10}:{:<5} {:>32}",
+                tt_label,
+                "line".bright_cyan(),
+                line.to_string().green(),
+                text.yellow(),
+            );
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::lexer::{Span, Token};
+use crate::schedule::{Dialect, Schedule};
+
+//------------------------------------------------------------------------------
+// Source positions
+//------------------------------------------------------------------------------
+
+/// Maps byte offsets in the crontab source to 1-based lines and columns,
+/// columns counted in characters.
+pub struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    pub fn new(source: &'a str) -> LineIndex<'a> {
+        let mut starts = vec![0];
+        starts.extend(source.match_indices('\n').map(|(idx, _)| idx + 1));
+        LineIndex { source, starts }
+    }
+
+    pub fn line(&self, offset: usize) -> usize {
+        self.starts.partition_point(|&start| start <= offset)
+    }
+
+    pub fn column(&self, offset: usize) -> usize {
+        let start = self.starts[self.line(offset) - 1];
+        self.source[start..offset].chars().count() + 1
+    }
+}
+
+//------------------------------------------------------------------------------
+// Crontab structure
+//------------------------------------------------------------------------------
+
+/// An environment assignment line such as `MAILTO=root`.
+#[derive(Debug, Clone)]
+pub struct Assignment {
+    pub line: usize,
+    pub name: String,
+    pub value: String,
+}
+
+/// A scheduled job: the time fields (or `@` nickname) followed by a command.
+#[derive(Debug, Clone)]
+pub struct Entry {
+    pub line: usize,
+    pub schedule_text: String,
+    pub schedule: Schedule,
+    pub command: String,
+    pub command_span: Span,
+    pub command_tokens: Vec<(Token, Span)>,
+}
+
+/// A line that looks like an entry but couldn't be understood.
+#[derive(Debug, Clone)]
+pub struct ParseError {
+    pub span: Span,
+    pub message: String,
+}
+
+#[derive(Debug, Default)]
+pub struct Crontab {
+    pub assignments: Vec<Assignment>,
+    pub entries: Vec<Entry>,
+    pub errors: Vec<ParseError>,
+}
+
+//------------------------------------------------------------------------------
+// Parser
+//------------------------------------------------------------------------------
+
+/// Group the lexer's tokens into lines and interpret each line.
+///
+/// The lexer splits schedule fields into their parts (`0-30/5` comes out
+/// as INT DASH INT SLASH INT), so the time fields are re-read from the
+/// source text of each whitespace separated group of tokens instead of
+/// from the tokens.
+pub fn parse(source: &str, tokens: &[(Token, Span)], dialect: Dialect) -> Crontab {
+    let index = LineIndex::new(source);
+    let mut crontab = Crontab::default();
+
+    // tokens never span lines, so each line is a contiguous run of them
+    let lines = tokens.chunk_by(|a, b| index.line(a.1.start) == index.line(b.1.start));
+    for tokens in lines {
+        let line = index.line(tokens[0].1.start);
+        let span = tokens[0].1.start..tokens[tokens.len() - 1].1.end;
+        let text = &source[span.clone()];
+
+        if let Some(assignment) = parse_assignment(line, text) {
+            crontab.assignments.push(assignment);
+            continue;
+        }
+
+        match parse_entry(source, line, span.clone(), tokens, dialect) {
+            Ok(entry) => crontab.entries.push(entry),
+            Err(error) => crontab.errors.push(error),
+        }
+    }
+
+    crontab
+}
+
+fn parse_assignment(line: usize, text: &str) -> Option<Assignment> {
+    let (name, value) = text.split_once('=')?;
+    let name = name.trim_end();
+    let mut chars = name.chars();
+    let first = chars.next()?;
+    if !(first.is_ascii_alphabetic() || first == '_')
+        || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+    {
+        return None;
+    }
+
+    let value = value.trim();
+    let unquoted = ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(value);
+
+    Some(Assignment {
+        line,
+        name: name.to_string(),
+        value: unquoted.to_string(),
+    })
+}
+
+fn parse_entry(
+    source: &str,
+    line: usize,
+    span: Span,
+    tokens: &[(Token, Span)],
+    dialect: Dialect,
+) -> Result<Entry, ParseError> {
+    //
+    // Split into fields: runs of tokens with no whitespace between them
+    //
+    // (each field remembers the index of its last token)
+    let mut fields: Vec<(Span, usize)> = Vec::new();
+    for (idx, (_, token_span)) in tokens.iter().enumerate() {
+        match fields.last_mut() {
+            Some((field, last)) if field.end == token_span.start => {
+                field.end = token_span.end;
+                *last = idx;
+            }
+            _ => fields.push((token_span.clone(), idx)),
+        }
+    }
+
+    let error = |span: Span, message: String| ParseError { span, message };
+
+    let first = &source[fields[0].0.clone()];
+    let schedule_fields = if first.starts_with('@') {
+        1
+    } else if dialect.has_year()
+        && fields.len() > dialect.field_count() + 1
+        && looks_like_year(&source[fields[dialect.field_count()].0.clone()])
+    {
+        dialect.field_count() + 1
+    } else {
+        dialect.field_count()
+    };
+    if fields.len() <= schedule_fields {
+        return Err(error(span, "entry has no command".to_string()));
+    }
+    let command_span = fields[schedule_fields].0.start..span.end;
+    let command = &source[command_span.clone()];
+
+    let schedule = if schedule_fields == 1 {
+        Schedule::from_nickname(first, dialect, command).ok_or_else(|| {
+            let message = format!("unknown schedule nickname `{first}` for {} cron", dialect.name());
+            error(fields[0].0.clone(), message)
+        })?
+    } else {
+        let texts: Vec<&str> = fields[..schedule_fields]
+            .iter()
+            .map(|(f, _)| &source[f.clone()])
+            .collect();
+        Schedule::parse(&texts, dialect, command)
+            .map_err(|e| error(fields[e.field].0.clone(), e.message))?
+    };
+
+    let schedule_span = fields[0].0.start..fields[schedule_fields - 1].0.end;
+    let command_tokens = tokens[fields[schedule_fields - 1].1 + 1..].to_vec();
+
+    Ok(Entry {
+        line,
+        schedule_text: source[schedule_span].to_string(),
+        schedule,
+        command: command.to_string(),
+        command_span,
+        command_tokens,
+    })
+}
+
+/// Quartz's year field is optional, so a seventh field is only taken as the
+/// year if it reads like one (`2030`, `2025-2030`, `*`) rather than a command.
+fn looks_like_year(text: &str) -> bool {
+    let year_chars = text.chars().all(|c| c.is_ascii_digit() || matches!(c, '*' | ',' | '-' | '/'));
+    let leading_digits = text.chars().take_while(char::is_ascii_digit).count();
+    year_chars && (text.starts_with('*') || leading_digits == 4)
+}
+
+impl Crontab {
+    /// The environment an entry runs with: assignments above it, later ones
+    /// overriding earlier ones of the same name.
+    pub fn environment_for(&self, entry: &Entry) -> Vec<&Assignment> {
+        let mut env: Vec<&Assignment> = Vec::new();
+        for assignment in self.assignments.iter().filter(|a| a.line < entry.line) {
+            env.retain(|a| a.name != assignment.name);
+            env.push(assignment);
+        }
+        env
+    }
+}
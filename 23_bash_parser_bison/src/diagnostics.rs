@@ -0,0 +1,175 @@
+use crate::crontab::LineIndex;
+use crate::lexer::Span;
+use serde_json::{json, Value};
+
+/// How findings are reported: the original token dump plus bare paths, or a
+/// machine readable document for editors and code review tools.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Text,
+    Json,
+    Sarif,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+    Note,
+}
+
+impl Severity {
+    // these double as SARIF `level` values
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+            Severity::Note => "note",
+        }
+    }
+}
+
+/// Rule IDs and the descriptions published in the SARIF rule table.
+pub const RULES: [(&str, &str); 4] = [
+    ("lex-error", "The crontab text could not be tokenized"),
+    ("invalid-entry", "A line is neither a valid crontab entry nor an assignment"),
+    ("missing-path", "A path referenced by a command does not exist"),
+    ("path-reference", "A path referenced by a command"),
+];
+
+/// One finding, positioned by 1-based line and column (end exclusive).
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub rule: &'static str,
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub end_column: usize,
+    pub message: String,
+}
+
+impl Diagnostic {
+    pub fn new(
+        rule: &'static str,
+        severity: Severity,
+        index: &LineIndex,
+        span: &Span,
+        message: String,
+    ) -> Diagnostic {
+        let line = index.line(span.start);
+        let column = index.column(span.start);
+        // tokens never cross lines, but a span ending on the newline might
+        let end_column = if index.line(span.end) == line {
+            index.column(span.end)
+        } else {
+            column + 1
+        };
+        Diagnostic {
+            rule,
+            severity,
+            line,
+            column,
+            end_column: end_column.max(column + 1),
+            message,
+        }
+    }
+
+    /// `file:line:col: severity[rule]: message` followed by the offending
+    /// line with the span underlined:
+    ///
+    /// ```text
+    ///  7 | 0 5 * * * echo "hello
+    ///    |                ^
+    /// ```
+    pub fn to_text(&self, file: &str, source: &str) -> String {
+        let mut out = format!(
+            "{file}:{}:{}: {}[{}]: {}",
+            self.line,
+            self.column,
+            self.severity.as_str(),
+            self.rule,
+            self.message
+        );
+        let Some(text) = source.lines().nth(self.line - 1) else {
+            return out;
+        };
+
+        // keep tabs so the carets line up with the text above them
+        let padding: String = text
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let carets = "^".repeat(self.end_column - self.column);
+        let gutter = " ".repeat(self.line.to_string().len());
+        out.push_str(&format!("\n {} | {text}\n {gutter} | {padding}{carets}", self.line));
+        out
+    }
+}
+
+/// Render diagnostics as a JSON array or a SARIF 2.1.0 log.
+pub fn render(diagnostics: &[Diagnostic], file: &str, format: Format) -> String {
+    let document = match format {
+        Format::Sarif => sarif(diagnostics, file),
+        _ => Value::Array(diagnostics.iter().map(|d| to_json(d, file)).collect()),
+    };
+    serde_json::to_string_pretty(&document).expect("JSON values always serialize")
+}
+
+fn to_json(diagnostic: &Diagnostic, file: &str) -> Value {
+    json!({
+        "file": file,
+        "line": diagnostic.line,
+        "column": diagnostic.column,
+        "endColumn": diagnostic.end_column,
+        "rule": diagnostic.rule,
+        "severity": diagnostic.severity.as_str(),
+        "message": diagnostic.message,
+    })
+}
+
+fn sarif(diagnostics: &[Diagnostic], file: &str) -> Value {
+    let rules: Vec<Value> = RULES
+        .iter()
+        .map(|(id, description)| json!({ "id": id, "shortDescription": { "text": description } }))
+        .collect();
+    let results: Vec<Value> = diagnostics
+        .iter()
+        .map(|d| {
+            json!({
+                "ruleId": d.rule,
+                "ruleIndex": RULES.iter().position(|(id, _)| *id == d.rule),
+                "level": d.severity.as_str(),
+                "message": { "text": d.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file },
+                        "region": {
+                            "startLine": d.line,
+                            "startColumn": d.column,
+                            "endLine": d.line,
+                            "endColumn": d.end_column,
+                        }
+                    }
+                }]
+            })
+        })
+        .collect();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": env!("CARGO_PKG_NAME"),
+                    "version": env!("CARGO_PKG_VERSION"),
+                    "rules": rules,
+                }
+            },
+            "columnKind": "unicodeCodePoints",
+            "results": results,
+        }]
+    })
+}
+
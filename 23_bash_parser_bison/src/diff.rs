@@ -0,0 +1,123 @@
+use crate::crontab::{Crontab, Entry};
+use chrono::{NaiveDateTime, Timelike};
+use std::collections::BTreeMap;
+
+// How many upcoming runs to show for each side of a change
+const NEXT_RUNS: usize = 3;
+
+/// How one job differs between two versions of a crontab. Jobs are matched
+/// by their command text.
+pub enum Change<'a> {
+    Added(&'a Entry),
+    Removed(&'a Entry),
+    /// Same command, different firing times
+    Rescheduled { old: &'a Entry, new: &'a Entry },
+    /// Same command and firing times, written differently
+    Rewritten { old: &'a Entry, new: &'a Entry },
+}
+
+impl Change<'_> {
+    /// Everything except a rewrite changes when jobs run.
+    pub fn is_semantic(&self) -> bool {
+        !matches!(self, Change::Rewritten { .. })
+    }
+
+    fn line(&self) -> usize {
+        match self {
+            Change::Added(entry) | Change::Removed(entry) => entry.line,
+            Change::Rescheduled { new, .. } | Change::Rewritten { new, .. } => new.line,
+        }
+    }
+}
+
+/// Compare the entries of two crontabs. Entries with the same command are
+/// paired in file order; extra ones on either side are added or removed.
+pub fn diff<'a>(old: &'a Crontab, new: &'a Crontab) -> Vec<Change<'a>> {
+    let mut by_command: BTreeMap<&str, (Vec<&Entry>, Vec<&Entry>)> = BTreeMap::new();
+    for entry in &old.entries {
+        by_command.entry(&entry.command).or_default().0.push(entry);
+    }
+    for entry in &new.entries {
+        by_command.entry(&entry.command).or_default().1.push(entry);
+    }
+
+    let mut changes = Vec::new();
+    for (olds, news) in by_command.into_values() {
+        for pair in 0..olds.len().max(news.len()) {
+            let change = match (olds.get(pair), news.get(pair)) {
+                (Some(old), Some(new)) if old.schedule_text == new.schedule_text => continue,
+                (Some(old), Some(new)) if old.schedule.equivalent(&new.schedule) => {
+                    Change::Rewritten { old, new }
+                }
+                (Some(old), Some(new)) => Change::Rescheduled { old, new },
+                (Some(old), None) => Change::Removed(old),
+                (None, Some(new)) => Change::Added(new),
+                (None, None) => unreachable!(),
+            };
+            changes.push(change);
+        }
+    }
+    changes.sort_by_key(|change| change.line());
+    changes
+}
+
+//------------------------------------------------------------------------------
+// Report
+//------------------------------------------------------------------------------
+
+pub fn print_report(changes: &[Change], now: NaiveDateTime) {
+    for change in changes {
+        match change {
+            Change::Added(entry) => {
+                println!("+ line {}: {} {}", entry.line, entry.schedule_text, entry.command);
+                println!("    next: {}", next_runs(entry, now));
+            }
+            Change::Removed(entry) => {
+                println!("- old line {}: {} {}", entry.line, entry.schedule_text, entry.command);
+                println!("    was next: {}", next_runs(entry, now));
+            }
+            Change::Rescheduled { old, new } => {
+                println!("~ line {} (was {}): {}", new.line, old.line, new.command);
+                println!("    old: {:<20} next: {}", old.schedule_text, next_runs(old, now));
+                println!("    new: {:<20} next: {}", new.schedule_text, next_runs(new, now));
+            }
+            Change::Rewritten { old, new } => {
+                println!("= line {} (was {}): {}", new.line, old.line, new.command);
+                println!(
+                    "    `{}` is equivalent to `{}`",
+                    new.schedule_text, old.schedule_text
+                );
+            }
+        }
+    }
+
+    let count = |f: fn(&Change) -> bool| changes.iter().filter(|c| f(c)).count();
+    println!(
+        "{} added, {} removed, {} rescheduled, {} rewritten but equivalent",
+        count(|c| matches!(c, Change::Added(_))),
+        count(|c| matches!(c, Change::Removed(_))),
+        count(|c| matches!(c, Change::Rescheduled { .. })),
+        count(|c| matches!(c, Change::Rewritten { .. })),
+    );
+}
+
+fn next_runs(entry: &Entry, now: NaiveDateTime) -> String {
+    if entry.schedule.reboot {
+        return "at boot".to_string();
+    }
+    let mut runs = Vec::new();
+    let mut t = now;
+    while runs.len() < NEXT_RUNS {
+        let Some(next) = entry.schedule.next_after(t) else {
+            break;
+        };
+        // seconds only matter to the dialects that have a seconds field
+        let format = if next.second() == 0 { "%a %Y-%m-%d %H:%M" } else { "%a %Y-%m-%d %H:%M:%S" };
+        runs.push(next.format(format).to_string());
+        t = next;
+    }
+    if runs.is_empty() {
+        return "never".to_string();
+    }
+    runs.join(", ")
+}
@@ -0,0 +1,395 @@
+use crate::crontab::Entry;
+use chrono::{DateTime, Datelike, Duration, Local, NaiveDateTime, Utc};
+use serde_json::Value;
+
+// A run logged this long after a scheduled firing still counts as that
+// firing; cron logs the CMD line within a second or two of starting.
+const GRACE_MINUTES: i64 = 2;
+// Missed firings listed per entry before summarising
+const MAX_LISTED: usize = 5;
+
+/// One `CMD` line from a cron log.
+#[derive(Debug, Clone)]
+pub struct Run {
+    pub time: NaiveDateTime,
+    pub user: String,
+    pub command: String,
+}
+
+/// Parse exported cron logs: syslog lines such as
+///
+/// ```text
+/// Oct 18 05:00:01 host CRON[1234]: (root) CMD (/usr/local/bin/backup.sh)
+/// ```
+///
+/// with a traditional or RFC 3339 timestamp, or `journalctl -o json` output
+/// with one object per line. Lines that aren't cron `CMD` lines are ignored.
+///
+/// Traditional syslog timestamps have no year, so they're placed in the
+/// year before `now` if they'd otherwise be in the future. Times are local.
+pub fn parse_log(text: &str, now: NaiveDateTime) -> Vec<Run> {
+    let mut runs: Vec<Run> = text
+        .lines()
+        .filter_map(|line| {
+            if line.trim_start().starts_with('{') {
+                parse_journal_line(line)
+            } else {
+                parse_syslog_line(line, now)
+            }
+        })
+        .collect();
+    runs.sort_by_key(|run| run.time);
+    runs
+}
+
+fn parse_syslog_line(line: &str, now: NaiveDateTime) -> Option<Run> {
+    let (user, command) = parse_message(line)?;
+    let time = match DateTime::parse_from_rfc3339(line.split_whitespace().next()?) {
+        Ok(time) => time.with_timezone(&Local).naive_local(),
+        Err(_) => {
+            // `Oct 18 05:00:01`, the day space padded. Parsed with each
+            // candidate year so that Feb 29 is only read in a leap year.
+            let stamp = line.split_whitespace().take(3).collect::<Vec<_>>().join(" ");
+            [now.year(), now.year() - 1].into_iter().find_map(|year| {
+                NaiveDateTime::parse_from_str(&format!("{year} {stamp}"), "%Y %b %d %H:%M:%S")
+                    .ok()
+                    .filter(|time| *time <= now)
+            })?
+        }
+    };
+    Some(Run {
+        time,
+        user,
+        command,
+    })
+}
+
+fn parse_journal_line(line: &str) -> Option<Run> {
+    let record: Value = serde_json::from_str(line).ok()?;
+    let message = record.get("MESSAGE")?.as_str()?;
+    let (user, command) = parse_message(message)?;
+    // microseconds since the epoch, as a string
+    let micros: i64 = record.get("__REALTIME_TIMESTAMP")?.as_str()?.parse().ok()?;
+    let time = DateTime::<Utc>::from_timestamp_micros(micros)?
+        .with_timezone(&Local)
+        .naive_local();
+    Some(Run {
+        time,
+        user,
+        command,
+    })
+}
+
+/// `(root) CMD (/usr/bin/backup)` -> (`root`, `/usr/bin/backup`)
+fn parse_message(message: &str) -> Option<(String, String)> {
+    let at = message.find(") CMD (")?;
+    let user = &message[message[..at].rfind('(')? + 1..at];
+    let command = message[at + ") CMD (".len()..].trim_end();
+    let command = command.strip_suffix(')')?;
+    Some((user.to_string(), command.to_string()))
+}
+
+//------------------------------------------------------------------------------
+// Report
+//------------------------------------------------------------------------------
+
+/// Whether a logged run is of `entry`. Some crons truncate long commands
+/// in the log, so a logged prefix of the command also counts.
+fn runs_entry(run: &Run, entry: &Entry) -> bool {
+    run.command == entry.command
+        || (run.command.len() >= 32 && entry.command.starts_with(&run.command))
+}
+
+/// One entry's logged runs and the firings with no run.
+pub struct EntryHistory<'a> {
+    pub entry: &'a Entry,
+    pub runs: Vec<&'a Run>,
+    pub missed: Vec<NaiveDateTime>,
+}
+
+/// The entries' runs over the period a log covers, and the logged runs no
+/// entry accounts for.
+pub struct History<'a> {
+    pub from: NaiveDateTime,
+    pub entries: Vec<EntryHistory<'a>>,
+    pub unexpected: Vec<&'a Run>,
+}
+
+/// Match `runs` to `entries`, `None` if there are no runs to go on.
+pub fn reconcile<'a>(entries: &'a [Entry], runs: &'a [Run]) -> Option<History<'a>> {
+    let (first, last) = (runs.first()?, runs.last()?);
+    // firings right at the end may not have been logged yet
+    let (from, to) = (first.time, last.time - Duration::minutes(GRACE_MINUTES));
+
+    let entries = entries
+        .iter()
+        .map(|entry| {
+            let own: Vec<&Run> = runs.iter().filter(|run| runs_entry(run, entry)).collect();
+            // Firings and runs are both in time order, so walk them together
+            // rather than searching every run for every firing of a busy
+            // entry over a long log.
+            let mut missed = Vec::new();
+            let mut next_run = 0;
+            let mut firing = entry.schedule.next_after(from - Duration::seconds(1));
+            while let Some(at) = firing.filter(|at| *at < to) {
+                // runs before this firing can't be its run, nor any later one's
+                next_run += own[next_run..].partition_point(|run| run.time < at);
+                if own
+                    .get(next_run)
+                    .is_none_or(|run| run.time >= at + Duration::minutes(GRACE_MINUTES))
+                {
+                    missed.push(at);
+                }
+                firing = entry.schedule.next_after(at);
+            }
+            EntryHistory {
+                entry,
+                runs: own,
+                missed,
+            }
+        })
+        .collect::<Vec<_>>();
+    let unexpected = runs
+        .iter()
+        .filter(|run| !entries.iter().any(|history| runs_entry(run, history.entry)))
+        .collect();
+    Some(History {
+        from,
+        entries,
+        unexpected,
+    })
+}
+
+/// Print each entry's last run and its missed firings within the period the
+/// log covers, then logged commands no entry accounts for. Returns whether
+/// any firing was missed.
+pub fn print_report(entries: &[Entry], runs: &[Run]) -> bool {
+    let Some(history) = reconcile(entries, runs) else {
+        println!("No cron CMD lines found in the log");
+        return false;
+    };
+
+    println!(
+        "Log covers {} .. {} ({} runs)",
+        history.from.format("%Y-%m-%d %H:%M"),
+        runs[runs.len() - 1].time.format("%Y-%m-%d %H:%M"),
+        runs.len()
+    );
+    println!("{:>5}  {:<20} {:>5} {:>6}  entry", "line", "last run", "runs", "missed");
+
+    let mut missed_lines = Vec::new();
+    for EntryHistory {
+        entry,
+        runs: own,
+        missed,
+    } in &history.entries
+    {
+        let last_run = own
+            .last()
+            .map_or("never".to_string(), |run| run.time.format("%a %Y-%m-%d %H:%M").to_string());
+        println!(
+            "{:>5}  {last_run:<20} {:>5} {:>6}  {} {}",
+            entry.line,
+            own.len(),
+            missed.len(),
+            entry.schedule_text,
+            entry.command
+        );
+
+        if !missed.is_empty() {
+            let mut listed: Vec<String> = missed
+                .iter()
+                .take(MAX_LISTED)
+                .map(|time| time.format("%a %Y-%m-%d %H:%M").to_string())
+                .collect();
+            if missed.len() > MAX_LISTED {
+                listed.push(format!("... and {} more", missed.len() - MAX_LISTED));
+            }
+            missed_lines.push(format!("  line {}: {}", entry.line, listed.join(", ")));
+        }
+    }
+
+    if !missed_lines.is_empty() {
+        println!();
+        println!("Missed runs:");
+        for line in &missed_lines {
+            println!("{line}");
+        }
+    }
+
+    let unknown = &history.unexpected;
+    if !unknown.is_empty() {
+        println!();
+        println!("Logged runs matching no entry: {}", unknown.len());
+        let mut commands: Vec<(&str, &str)> = unknown
+            .iter()
+            .map(|run| (run.user.as_str(), run.command.as_str()))
+            .collect();
+        commands.sort_unstable();
+        commands.dedup();
+        for (user, command) in commands.iter().take(MAX_LISTED) {
+            println!("  ({user}) {command}");
+        }
+        if commands.len() > MAX_LISTED {
+            println!("  ... and {} more", commands.len() - MAX_LISTED);
+        }
+    }
+
+    !missed_lines.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crontab;
+    use crate::lexer;
+    use crate::schedule::Dialect;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").expect("a valid time")
+    }
+
+    fn entries(source: &str) -> Vec<Entry> {
+        let (tokens, _) = lexer::lex(source, Dialect::Vixie);
+        crontab::parse(source, &tokens, Dialect::Vixie).entries
+    }
+
+    fn run(time: &str, command: &str) -> Run {
+        Run {
+            time: at(time),
+            user: "root".to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    #[test]
+    fn parses_syslog_and_journal_lines() {
+        let now = at("2026-10-18 12:00:00");
+        let micros = at("2026-10-18 05:00:01")
+            .and_local_timezone(Local)
+            .single()
+            .expect("an unambiguous local time")
+            .timestamp_micros();
+        let log = format!(
+            "Oct 18 05:00:01 host CRON[1234]: (root) CMD (/usr/local/bin/backup.sh)\n\
+             Dec 31 23:59:01 host CRON[99]: (alice) CMD (echo (nested))\n\
+             Oct 18 05:00:01 host sshd[1]: Accepted publickey for root\n\
+             {{\"MESSAGE\": \"(bob) CMD (/usr/bin/report)\", \"__REALTIME_TIMESTAMP\": \"{micros}\"}}\n\
+             {{\"MESSAGE\": \"pam_unix(cron:session): session opened\"}}\n"
+        );
+
+        let runs = parse_log(&log, now);
+        let summary: Vec<(NaiveDateTime, &str, &str)> = runs
+            .iter()
+            .map(|run| (run.time, run.user.as_str(), run.command.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            [
+                // no year in the log, and Dec 31 would be in the future
+                (at("2025-12-31 23:59:01"), "alice", "echo (nested)"),
+                (at("2026-10-18 05:00:01"), "root", "/usr/local/bin/backup.sh"),
+                (at("2026-10-18 05:00:01"), "bob", "/usr/bin/report"),
+            ]
+        );
+    }
+
+    #[test]
+    fn matched_runs_leave_nothing_missed() {
+        let entries = entries("0 * * * * /usr/bin/hourly\n");
+        let runs = [
+            run("2026-10-18 10:00:01", "/usr/bin/hourly"),
+            run("2026-10-18 11:00:02", "/usr/bin/hourly"),
+            run("2026-10-18 12:00:01", "/usr/bin/hourly"),
+        ];
+        let history = reconcile(&entries, &runs).expect("runs to go on");
+        assert_eq!(history.entries[0].runs.len(), 3);
+        assert!(history.entries[0].missed.is_empty());
+        assert!(history.unexpected.is_empty());
+    }
+
+    #[test]
+    fn firings_without_a_run_are_missed() {
+        let entries = entries("0 * * * * /usr/bin/hourly\n30 * * * * /usr/bin/never\n");
+        let runs = [
+            run("2026-10-18 10:00:01", "/usr/bin/hourly"),
+            // 11:00 didn't run, and 12:05 is too late to count for 12:00
+            run("2026-10-18 12:05:00", "/usr/bin/hourly"),
+            run("2026-10-18 13:00:01", "/usr/bin/hourly"),
+        ];
+        let history = reconcile(&entries, &runs).expect("runs to go on");
+        assert_eq!(
+            history.entries[0].missed,
+            [at("2026-10-18 11:00:00"), at("2026-10-18 12:00:00")]
+        );
+        assert_eq!(
+            history.entries[1].missed,
+            [
+                at("2026-10-18 10:30:00"),
+                at("2026-10-18 11:30:00"),
+                at("2026-10-18 12:30:00")
+            ]
+        );
+    }
+
+    #[test]
+    fn runs_matching_no_entry_are_unexpected() {
+        let long = "/usr/local/bin/nightly-report --format html --to ops@example.com";
+        let entries = entries(&format!("0 * * * * /usr/bin/hourly\n0 0 * * * {long}\n"));
+        let runs = [
+            run("2026-10-18 00:00:01", &long[..40]),
+            run("2026-10-18 00:00:01", "/usr/bin/hourly"),
+            run("2026-10-18 00:17:00", "/tmp/miner"),
+            // a short prefix isn't taken as truncation
+            run("2026-10-18 01:00:01", "/usr/bin/hour"),
+        ];
+        let history = reconcile(&entries, &runs).expect("runs to go on");
+        assert_eq!(history.entries[1].runs.len(), 1);
+        let unexpected: Vec<&str> = history
+            .unexpected
+            .iter()
+            .map(|run| run.command.as_str())
+            .collect();
+        assert_eq!(unexpected, ["/tmp/miner", "/usr/bin/hour"]);
+    }
+
+    #[test]
+    fn feb_29_is_read_in_the_last_leap_year() {
+        let log = "Feb 29 05:00:01 host CRON[1]: (root) CMD (/usr/bin/leap)\n";
+        let runs = parse_log(log, at("2025-03-01 12:00:00"));
+        assert_eq!(runs[0].time, at("2024-02-29 05:00:01"));
+        let runs = parse_log(log, at("2028-02-29 12:00:00"));
+        assert_eq!(runs[0].time, at("2028-02-29 05:00:01"));
+    }
+
+    #[test]
+    fn long_every_minute_log() {
+        let entries = entries("* * * * * /usr/bin/poll\n");
+        // four weeks of runs a second past each minute, except for a ten
+        // minute outage
+        let start = at("2026-09-20 00:00:01");
+        let outage = at("2026-10-04 12:00:00")..at("2026-10-04 12:10:00");
+        let runs: Vec<Run> = (0..28 * 24 * 60)
+            .map(|minute| start + Duration::minutes(minute))
+            .filter(|time| !outage.contains(time))
+            .map(|time| Run {
+                time,
+                user: "root".to_string(),
+                command: "/usr/bin/poll".to_string(),
+            })
+            .collect();
+
+        let history = reconcile(&entries, &runs).expect("runs to go on");
+        assert_eq!(history.entries[0].runs.len(), 28 * 24 * 60 - 10);
+        // 12:10:01 still counts for the 12:09 firing
+        let missed: Vec<NaiveDateTime> = (0..9)
+            .map(|minute| at("2026-10-04 12:00:00") + Duration::minutes(minute))
+            .collect();
+        assert_eq!(history.entries[0].missed, missed);
+    }
+
+    #[test]
+    fn no_runs_no_history() {
+        assert!(reconcile(&entries("0 * * * * /usr/bin/hourly\n"), &[]).is_none());
+    }
+}
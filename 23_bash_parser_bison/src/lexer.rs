@@ -0,0 +1,387 @@
+use crate::schedule::Dialect;
+use chumsky::prelude::*;
+
+/// Byte range of a token in the crontab source.
+pub type Span = std::ops::Range<usize>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Token {
+    Star,
+    Slash,
+    Comma,
+    Dash,
+    MonthName(String),
+    DowName(String),
+    Int(i64),
+    HttpUrl(String),
+    SshUrl(String),
+    Url(String),
+    Path(String),
+    StringLiteral(String),
+    CliOption(String),
+    Program(String),
+    Variable(String),
+    Equals,
+    Redirect(String),
+    Async,
+    /// Quartz/Spring nth weekday such as `6#3` or `FRI#3`
+    NthWeekday(String),
+}
+
+//------------------------------------------------------------------------------
+// Lexer definition
+//------------------------------------------------------------------------------
+
+// (the lexer only produces a stream of tokens, see crontab.rs for the entries)
+//
+// Lexing stops at the first character no token rule accepts instead of
+// requiring end of input, so lex() can point at exactly where a line went
+// wrong. The dialect decides whether `#` after a weekday is the nth weekday
+// syntax or starts a comment.
+pub fn cron_lexer(dialect: Dialect) -> impl Parser<char, Vec<(Token, Span)>, Error = Simple<char>> {
+    let month = choice((
+        just("JAN").map(|_| Token::MonthName("JAN".to_string())),
+        just("FEB").map(|_| Token::MonthName("FEB".to_string())),
+        just("MAR").map(|_| Token::MonthName("MAR".to_string())),
+        just("APR").map(|_| Token::MonthName("APR".to_string())),
+        just("MAY").map(|_| Token::MonthName("MAY".to_string())),
+        just("JUN").map(|_| Token::MonthName("JUN".to_string())),
+        just("JUL").map(|_| Token::MonthName("JUL".to_string())),
+        just("AUG").map(|_| Token::MonthName("AUG".to_string())),
+        just("SEP").map(|_| Token::MonthName("SEP".to_string())),
+        just("OCT").map(|_| Token::MonthName("OCT".to_string())),
+        just("NOV").map(|_| Token::MonthName("NOV".to_string())),
+        just("DEC").map(|_| Token::MonthName("DEC".to_string())),
+    ));
+
+    let dow = choice((
+        just("SUN").map(|_| Token::DowName("SUN".to_string())),
+        just("MON").map(|_| Token::DowName("MON".to_string())),
+        just("TUE").map(|_| Token::DowName("TUE".to_string())),
+        just("WED").map(|_| Token::DowName("WED".to_string())),
+        just("THU").map(|_| Token::DowName("THU".to_string())),
+        just("FRI").map(|_| Token::DowName("FRI".to_string())),
+        just("SAT").map(|_| Token::DowName("SAT".to_string())),
+    ));
+
+    // digit strings too large for i64 fail here and fall through to program
+    let int = text::int(10).try_map(|s: String, span| {
+        s.parse::<i64>()
+            .map(Token::Int)
+            .map_err(|_| Simple::custom(span, format!("integer {s} is too large")))
+    });
+
+    let nth_weekday = filter(|c: &char| c.is_ascii_alphanumeric())
+        .repeated()
+        .at_least(1)
+        .chain(just('#'))
+        .chain(filter(|c: &char| c.is_ascii_digit()))
+        .collect::<String>()
+        .try_map(move |s, span| {
+            if dialect.has_day_rules() {
+                Ok(Token::NthWeekday(s))
+            } else {
+                Err(Simple::custom(span, "`#` starts a comment in this dialect"))
+            }
+        });
+
+    let url_tail = none_of(" \t\r\n#")
+        .repeated()
+        .at_least(1)
+        .collect::<String>();
+
+    let http_url = choice((just("https://"), just("http://")))
+        .then(url_tail.clone())
+        .map(|(prefix, rest)| Token::HttpUrl(format!("{prefix}{rest}")));
+
+    let ssh_url = just("ssh://")
+        .then(url_tail.clone())
+        .map(|(prefix, rest)| Token::SshUrl(format!("{prefix}{rest}")));
+
+    let other_url = just("ftp://")
+        .then(url_tail.clone())
+        .map(|(prefix, rest)| Token::Url(format!("{prefix}{rest}")));
+
+    let escaped_char = just('\\').ignore_then(any());
+
+    let dq_inner = choice((escaped_char, none_of("\\\"\r\n")));
+    let sq_inner = choice((escaped_char, none_of("\\'\r\n")));
+
+    let quoted_string = choice((
+        dq_inner
+            .repeated()
+            .collect::<String>()
+            .delimited_by(just('"'), just('"')),
+        sq_inner
+            .repeated()
+            .collect::<String>()
+            .delimited_by(just('\''), just('\'')),
+    ));
+
+    let non_ws = filter(|c: &char| !c.is_whitespace() && *c != ';');
+    let non_ws_no_slash =
+        filter(|c: &char| !c.is_whitespace() && *c != '/' && *c != ';');
+    // not `=`, which belongs to the assignment before it
+    let rel_first_char = filter(|c: &char| {
+        !c.is_whitespace() && *c != '/' && *c != '*' && *c != ';' && *c != '='
+    });
+    let abs_first_char =
+        filter(|c: &char| {
+            !c.is_whitespace() && *c != '/' && *c != ';' && !c.is_ascii_digit()
+        });
+
+    let tilde_path = just('~')
+        .then(non_ws.repeated())
+        .map(|(tilde, rest)| {
+            let mut s = String::new();
+            s.push(tilde);
+            for c in rest {
+                s.push(c);
+            }
+            Token::Path(s)
+        });
+
+    let abs_path = just('/')
+        .then(
+            abs_first_char
+                .then(non_ws_no_slash.repeated())
+                .map(|(head, rest)| {
+                    let mut segment = Vec::new();
+                    segment.push(head);
+                    segment.extend(rest);
+                    segment
+                }),
+        )
+        .then(
+            just('/')
+                .then(non_ws_no_slash.repeated())
+                .repeated(),
+        )
+        .map(|((first_slash, first_segment), tail)| {
+            let mut s = String::new();
+            s.push(first_slash);
+            for c in first_segment {
+                s.push(c);
+            }
+            for (slash, segment) in tail {
+                s.push(slash);
+                for c in segment {
+                    s.push(c);
+                }
+            }
+            Token::Path(s)
+        });
+
+    let rel_path = rel_first_char
+        .then(non_ws_no_slash.repeated())
+        .map(|(head, rest)| {
+            let mut segment = Vec::new();
+            segment.push(head);
+            segment.extend(rest);
+            segment
+        })
+        .then(
+            just('/')
+                .then(non_ws_no_slash.repeated())
+                .repeated()
+                .at_least(1),
+        )
+        .try_map(|(first, tail), span| {
+            let mut s = String::new();
+            for c in first {
+                s.push(c);
+            }
+            for (slash, segment) in tail {
+                s.push(slash);
+                for c in segment {
+                    s.push(c);
+                }
+            }
+            // a stepped schedule field such as `0-30/10` or `1,15/2`, which
+            // falls through to int, dash, comma and slash
+            if s.chars().all(|c| c.is_ascii_digit() || matches!(c, '-' | ',' | '/')) {
+                return Err(Simple::custom(span, "a schedule field, not a path"));
+            }
+            Ok(Token::Path(s))
+        });
+
+    let path = choice((tilde_path, abs_path, rel_path));
+    let string_literal = quoted_string.map(Token::StringLiteral);
+    let identifier = filter(|c: &char| c.is_ascii_alphabetic() || *c == '_')
+        .then(
+            filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_')
+                .repeated(),
+        )
+        .map(|(first, rest)| {
+            let mut s = String::new();
+            s.push(first);
+            for c in rest {
+                s.push(c);
+            }
+            s
+        });
+    let variable = identifier
+        .then_ignore(just('=').rewind())
+        .map(Token::Variable);
+    let redirect_with_fd = filter(|c: &char| c.is_ascii_digit())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .then(
+            choice((
+                just(">>").to(">>".to_string()),
+                just(">").to(">".to_string()),
+            )),
+        )
+        .map(|(fd, op)| Token::Redirect(format!("{fd}{op}")))
+        .boxed();
+    let redirect_plain = choice((
+        just(">>").to(Token::Redirect(">>".to_string())),
+        just(">").to(Token::Redirect(">".to_string())),
+    ))
+    .boxed();
+    let redirect = choice((redirect_with_fd, redirect_plain));
+    let async_token = just('&').to(Token::Async);
+
+    let opt_char = filter(|c: &char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    let long_opt = just::<char, &str, Simple<char>>("--")
+        .ignore_then(filter(|c: &char| c.is_ascii_alphanumeric()))
+        .then(opt_char.repeated())
+        .map(|(first, rest)| {
+            let mut s = String::from("--");
+            s.push(first);
+            for c in rest {
+                s.push(c);
+            }
+            Token::CliOption(s)
+        });
+
+    // a leading quote that didn't close as a string literal is an error,
+    // not the start of a program name
+    let program = filter(|c: &char| !c.is_whitespace() && !matches!(c, '#' | '/' | '"' | '\''))
+        .chain(filter(|c: &char| !c.is_whitespace() && *c != '#' && *c != '/').repeated())
+        .collect::<String>()
+        .map(Token::Program);
+
+    let token = choice((
+        http_url,
+        ssh_url,
+        other_url,
+        string_literal,
+        // before path, otherwise `>/dev/null` and `2>/tmp/log` lex as paths
+        redirect,
+        // and `PATH=/usr/bin:/bin` as a relative path
+        variable,
+        path,
+        long_opt,
+        just('=').to(Token::Equals),
+        async_token,
+        just('*').to(Token::Star),
+        just('/').to(Token::Slash),
+        just(',').to(Token::Comma),
+        just('-').to(Token::Dash),
+        nth_weekday,
+        month,
+        dow,
+        int,
+        program,
+    ))
+    .boxed();
+
+    let comment = just('#')
+        .ignore_then(none_of("\r\n").repeated())
+        .ignored();
+
+    let skip = choice((filter(|c: &char| c.is_whitespace()).ignored(), comment))
+        .repeated()
+        .ignored();
+
+    token
+        .map_with_span(|token, span| (token, span))
+        .padded_by(skip.clone())
+        .repeated()
+        .then_ignore(skip)
+}
+
+pub fn token_label(token: &Token) -> &'static str {
+    match token {
+        Token::Star => "STAR",
+        Token::Slash => "SLASH",
+        Token::Comma => "COMMA",
+        Token::Dash => "DASH",
+        Token::MonthName(_) => "MONTH",
+        Token::DowName(_) => "DOW",
+        Token::Int(_) => "INT",
+        Token::HttpUrl(_) => "HTTP",
+        Token::SshUrl(_) => "SSH",
+        Token::Url(_) => "URL",
+        Token::Path(_) => "PATH",
+        Token::StringLiteral(_) => "STRING",
+        Token::CliOption(_) => "OPTION",
+        Token::Program(_) => "PROGRAM",
+        Token::Variable(_) => "VARIABLE",
+        Token::Equals => "EQUALS",
+        Token::Redirect(_) => "REDIRECT",
+        Token::Async => "ASYNC",
+        Token::NthWeekday(_) => "NTH",
+    }
+}
+
+/// A lexer failure with its byte span in the source.
+#[derive(Debug, Clone)]
+pub struct LexError {
+    pub span: Span,
+    pub message: String,
+}
+
+/// Lex `source` one line at a time, returning each token with its byte
+/// span. The part of a line that fails to lex is reported and skipped so
+/// the rest of the crontab is still lexed.
+///
+/// chumsky reports spans as char offsets, so they are translated back to
+/// byte offsets here to allow slicing the original text.
+pub fn lex(source: &str, dialect: Dialect) -> (Vec<(Token, Span)>, Vec<LexError>) {
+    let lexer = cron_lexer(dialect);
+    let mut tokens = Vec::new();
+    let mut errors = Vec::new();
+
+    let mut line_start = 0;
+    for line in source.split_inclusive('\n') {
+        let offsets: Vec<usize> = line
+            .char_indices()
+            .map(|(byte, _)| line_start + byte)
+            .chain(std::iter::once(line_start + line.len()))
+            .collect();
+
+        // cron_lexer() can't fail, it stops early instead
+        let line_tokens = lexer.parse(line).unwrap_or_default();
+        let lexed_to = line_tokens.last().map_or(0, |(_, span)| span.end);
+
+        let rest = &source[offsets[lexed_to]..line_start + line.len()];
+        let unlexed = rest.trim_start();
+        if !unlexed.is_empty() && !unlexed.starts_with('#') {
+            // drop the whole line rather than half an entry
+            let start = line_start + line.len() - unlexed.len();
+            errors.push(LexError {
+                span: start..start + unlexed.trim_end().len(),
+                message: describe(unlexed),
+            });
+        } else {
+            tokens.extend(
+                line_tokens
+                    .into_iter()
+                    .map(|(token, span)| (token, offsets[span.start]..offsets[span.end])),
+            );
+        }
+        line_start += line.len();
+    }
+    (tokens, errors)
+}
+
+fn describe(unlexed: &str) -> String {
+    match unlexed.chars().next() {
+        Some(quote @ ('"' | '\'')) => format!("unterminated string literal, missing closing {quote}"),
+        Some(c) => format!("unexpected `{c}`"),
+        None => "unexpected end of line".to_string(),
+    }
+}
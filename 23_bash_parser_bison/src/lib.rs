@@ -0,0 +1,13 @@
+//! croncheck's lexer, crontab parser and reports, shared by the binary and,
+//! with the `antlr` feature, the ANTLR differential oracle.
+
+#[cfg(feature = "antlr")]
+pub mod antlr;
+pub mod crontab;
+pub mod diagnostics;
+pub mod diff;
+pub mod history;
+pub mod lexer;
+pub mod schedule;
+pub mod systemd;
+pub mod timeline;
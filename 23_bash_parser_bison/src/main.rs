@@ -1,327 +1,133 @@
-use chumsky::prelude::*;
+use chrono::{Local, NaiveDate, NaiveDateTime, NaiveTime};
 use colored::Colorize;
-use std::{env, io::{self, Read}, path::Path};
-
-#[derive(Debug, Clone, PartialEq, Eq)]
-enum Token {
-    Star,
-    Slash,
-    Comma,
-    Dash,
-    MonthName(String),
-    DowName(String),
-    Int(i64),
-    HttpUrl(String),
-    SshUrl(String),
-    Url(String),
-    Path(String),
-    StringLiteral(String),
-    CliOption(String),
-    Program(String),
-    Variable(String),
-    Equals,
-    Redirect(String),
-    Async,
+use croncheck::crontab::{self, Crontab, LineIndex};
+use croncheck::diagnostics::{self, Diagnostic, Format, Severity};
+use croncheck::diff;
+use croncheck::history;
+use croncheck::lexer::{self, token_label, Span, Token};
+use croncheck::schedule::Dialect;
+use croncheck::systemd;
+use croncheck::timeline::{self, TimelineFormat, TimelineOptions};
+use std::{env, fs, io::{self, Read}, path::{Path, PathBuf}};
+
+/// What to do with the parsed crontab
+enum Mode {
+    /// Dump tokens and list referenced paths (the default)
+    Check,
+    /// Simulate this many days of firings
+    Timeline(i64),
+    /// Write systemd units into this directory
+    ToSystemd(PathBuf),
+    /// `croncheck diff OLD NEW`: compare two crontabs' schedules
+    Diff,
+    /// Match the runs in this cron log against the entries
+    History(PathBuf),
 }
 
 //------------------------------------------------------------------------------
-// Lexer definition
+// Main program
 //------------------------------------------------------------------------------
-
-// (we don't need a parser for this program, just a stream of tokens)
-fn cron_lexer() -> impl Parser<char, Vec<Token>, Error = Simple<char>> {
-    let month = choice((
-        just("JAN").map(|_| Token::MonthName("JAN".to_string())),
-        just("FEB").map(|_| Token::MonthName("FEB".to_string())),
-        just("MAR").map(|_| Token::MonthName("MAR".to_string())),
-        just("APR").map(|_| Token::MonthName("APR".to_string())),
-        just("MAY").map(|_| Token::MonthName("MAY".to_string())),
-        just("JUN").map(|_| Token::MonthName("JUN".to_string())),
-        just("JUL").map(|_| Token::MonthName("JUL".to_string())),
-        just("AUG").map(|_| Token::MonthName("AUG".to_string())),
-        just("SEP").map(|_| Token::MonthName("SEP".to_string())),
-        just("OCT").map(|_| Token::MonthName("OCT".to_string())),
-        just("NOV").map(|_| Token::MonthName("NOV".to_string())),
-        just("DEC").map(|_| Token::MonthName("DEC".to_string())),
-    ));
-
-    let dow = choice((
-        just("SUN").map(|_| Token::DowName("SUN".to_string())),
-        just("MON").map(|_| Token::DowName("MON".to_string())),
-        just("TUE").map(|_| Token::DowName("TUE".to_string())),
-        just("WED").map(|_| Token::DowName("WED".to_string())),
-        just("THU").map(|_| Token::DowName("THU".to_string())),
-        just("FRI").map(|_| Token::DowName("FRI".to_string())),
-        just("SAT").map(|_| Token::DowName("SAT".to_string())),
-    ));
-
-    let int = text::int(10).map(|s: String| {
-        let value = s
-            .parse::<i64>()
-            .expect("text::int(10) only produces digit strings");
-        Token::Int(value)
-    });
-
-    let url_tail = none_of(" \t\r\n#")
-        .repeated()
-        .at_least(1)
-        .collect::<String>();
-
-    let http_url = choice((just("https://"), just("http://")))
-        .then(url_tail.clone())
-        .map(|(prefix, rest)| Token::HttpUrl(format!("{prefix}{rest}")));
-
-    let ssh_url = just("ssh://")
-        .then(url_tail.clone())
-        .map(|(prefix, rest)| Token::SshUrl(format!("{prefix}{rest}")));
-
-    let other_url = just("ftp://")
-        .then(url_tail.clone())
-        .map(|(prefix, rest)| Token::Url(format!("{prefix}{rest}")));
-
-    let escaped_char = just('\\').ignore_then(any());
-
-    let dq_inner = choice((escaped_char.clone(), none_of("\\\"\r\n")));
-    let sq_inner = choice((escaped_char.clone(), none_of("\\'\r\n")));
-
-    let quoted_string = choice((
-        dq_inner
-            .repeated()
-            .collect::<String>()
-            .delimited_by(just('"'), just('"')),
-        sq_inner
-            .repeated()
-            .collect::<String>()
-            .delimited_by(just('\''), just('\'')),
-    ));
-
-    let non_ws = filter(|c: &char| !c.is_whitespace() && *c != ';');
-    let non_ws_no_slash =
-        filter(|c: &char| !c.is_whitespace() && *c != '/' && *c != ';');
-    let rel_first_char = filter(|c: &char| {
-        !c.is_whitespace() && *c != '/' && *c != '*' && *c != ';'
-    });
-    let abs_first_char =
-        filter(|c: &char| {
-            !c.is_whitespace() && *c != '/' && *c != ';' && !c.is_ascii_digit()
-        });
-
-    let tilde_path = just('~')
-        .then(non_ws.repeated())
-        .map(|(tilde, rest)| {
-            let mut s = String::new();
-            s.push(tilde);
-            for c in rest {
-                s.push(c);
+fn main() {
+    //
+    // Parse command-line arguments
+    //
+    let mut ignore_existing = false;
+    let mut mode = Mode::Check;
+    let mut format = Format::Text;
+    let mut dialect = Dialect::Vixie;
+    let mut input_files: Vec<String> = Vec::new();
+    let mut start: Option<NaiveDate> = None;
+    let mut timeline_format = TimelineFormat::Ascii;
+    let mut default_duration = 1;
+    let mut durations = Vec::new();
+    let mut hotspot_threshold = 3;
+
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "diff") {
+        args.next();
+        mode = Mode::Diff;
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ignore-existing" => ignore_existing = true,
+            "--timeline" => {
+                mode = match arg_value(&mut args, &arg).as_str() {
+                    "day" => Mode::Timeline(1),
+                    "week" => Mode::Timeline(7),
+                    other => usage_error(&format!("--timeline expects day or week, got {other}")),
+                }
             }
-            Token::Path(s)
-        });
-
-    let abs_path = just('/')
-        .then(
-            abs_first_char
-                .then(non_ws_no_slash.repeated())
-                .map(|(head, rest)| {
-                    let mut segment = Vec::new();
-                    segment.push(head);
-                    segment.extend(rest);
-                    segment
-                }),
-        )
-        .then(
-            just('/')
-                .then(non_ws_no_slash.repeated())
-                .repeated(),
-        )
-        .map(|((first_slash, first_segment), tail)| {
-            let mut s = String::new();
-            s.push(first_slash);
-            for c in first_segment {
-                s.push(c);
+            "--start" => {
+                let value = arg_value(&mut args, &arg);
+                start = Some(
+                    NaiveDate::parse_from_str(&value, "%Y-%m-%d")
+                        .unwrap_or_else(|_| usage_error(&format!("--start expects YYYY-MM-DD, got {value}"))),
+                );
             }
-            for (slash, segment) in tail {
-                s.push(slash);
-                for c in segment {
-                    s.push(c);
-                }
+            "--to-systemd" => mode = Mode::ToSystemd(PathBuf::from(arg_value(&mut args, &arg))),
+            "--history" => mode = Mode::History(PathBuf::from(arg_value(&mut args, &arg))),
+            "--csv" => timeline_format = TimelineFormat::Csv,
+            "--duration" => {
+                let value = arg_value(&mut args, &arg);
+                default_duration = value
+                    .parse()
+                    .unwrap_or_else(|_| usage_error(&format!("--duration expects minutes, got {value}")));
             }
-            Token::Path(s)
-        });
-
-    let rel_path = rel_first_char
-        .then(non_ws_no_slash.repeated())
-        .map(|(head, rest)| {
-            let mut segment = Vec::new();
-            segment.push(head);
-            segment.extend(rest);
-            segment
-        })
-        .then(
-            just('/')
-                .then(non_ws_no_slash.repeated())
-                .repeated()
-                .at_least(1),
-        )
-        .map(|(first, tail)| {
-            let mut s = String::new();
-            for c in first {
-                s.push(c);
+            "--durations" => {
+                let file = arg_value(&mut args, &arg);
+                let text = fs::read_to_string(&file)
+                    .unwrap_or_else(|e| usage_error(&format!("Failed to read {file}: {e}")));
+                durations = timeline::parse_durations(&text)
+                    .unwrap_or_else(|e| usage_error(&format!("{file}: {e}")));
             }
-            for (slash, segment) in tail {
-                s.push(slash);
-                for c in segment {
-                    s.push(c);
-                }
+            "--hotspot" => {
+                let value = arg_value(&mut args, &arg);
+                hotspot_threshold = value
+                    .parse()
+                    .unwrap_or_else(|_| usage_error(&format!("--hotspot expects a job count, got {value}")));
             }
-            Token::Path(s)
-        });
-
-    let path = choice((tilde_path, abs_path, rel_path));
-    let string_literal = quoted_string.map(Token::StringLiteral);
-    let identifier = filter(|c: &char| c.is_ascii_alphabetic() || *c == '_')
-        .then(
-            filter(|c: &char| c.is_ascii_alphanumeric() || *c == '_')
-                .repeated(),
-        )
-        .map(|(first, rest)| {
-            let mut s = String::new();
-            s.push(first);
-            for c in rest {
-                s.push(c);
+            "--format" => {
+                format = match arg_value(&mut args, &arg).as_str() {
+                    "text" => Format::Text,
+                    "json" => Format::Json,
+                    "sarif" => Format::Sarif,
+                    other => usage_error(&format!("--format expects text, json or sarif, got {other}")),
+                }
             }
-            s
-        });
-    let variable = identifier
-        .clone()
-        .then_ignore(just('=').rewind())
-        .map(Token::Variable);
-    let redirect_with_fd = filter(|c: &char| c.is_ascii_digit())
-        .repeated()
-        .at_least(1)
-        .collect::<String>()
-        .then(
-            choice((
-                just(">>").to(">>".to_string()),
-                just(">").to(">".to_string()),
-            )),
-        )
-        .map(|(fd, op)| Token::Redirect(format!("{fd}{op}")))
-        .boxed();
-    let redirect_plain = choice((
-        just(">>").to(Token::Redirect(">>".to_string())),
-        just(">").to(Token::Redirect(">".to_string())),
-    ))
-    .boxed();
-    let redirect = choice((redirect_with_fd, redirect_plain));
-    let async_token = just('&').to(Token::Async);
-
-    let opt_char = filter(|c: &char| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
-    let long_opt = just::<char, &str, Simple<char>>("--")
-        .ignore_then(filter(|c: &char| c.is_ascii_alphanumeric()))
-        .then(opt_char.repeated())
-        .map(|(first, rest)| {
-            let mut s = String::from("--");
-            s.push(first);
-            for c in rest {
-                s.push(c);
+            "--dialect" => {
+                let value = arg_value(&mut args, &arg);
+                dialect = Dialect::from_name(&value).unwrap_or_else(|| {
+                    usage_error(&format!(
+                        "--dialect expects vixie, kubernetes, jenkins, quartz or spring, got {value}"
+                    ))
+                });
             }
-            Token::CliOption(s)
-        });
-
-    let program = filter(|c: &char| !c.is_whitespace() && *c != '#' && *c != '/')
-        .repeated()
-        .at_least(1)
-        .collect::<String>()
-        .map(Token::Program);
-
-    let token = choice((
-        http_url,
-        ssh_url,
-        other_url,
-        string_literal,
-        path,
-        variable,
-        redirect,
-        long_opt,
-        just('=').to(Token::Equals),
-        async_token,
-        just('*').to(Token::Star),
-        just('/').to(Token::Slash),
-        just(',').to(Token::Comma),
-        just('-').to(Token::Dash),
-        month,
-        dow,
-        int,
-        program,
-    ))
-    .boxed();
-
-    let comment = just('#')
-        .ignore_then(none_of("\r\n").repeated())
-        .ignored();
-
-    let skip = choice((filter(|c: &char| c.is_whitespace()).ignored(), comment))
-        .repeated()
-        .ignored();
-
-    token
-        .padded_by(skip.clone())
-        .repeated()
-        .then_ignore(skip)
-        .then_ignore(end())
-}
-
-fn token_label(token: &Token) -> &'static str {
-    match token {
-        Token::Star => "STAR",
-        Token::Slash => "SLASH",
-        Token::Comma => "COMMA",
-        Token::Dash => "DASH",
-        Token::MonthName(_) => "MONTH",
-        Token::DowName(_) => "DOW",
-        Token::Int(_) => "INT",
-        Token::HttpUrl(_) => "HTTP",
-        Token::SshUrl(_) => "SSH",
-        Token::Url(_) => "URL",
-        Token::Path(_) => "PATH",
-        Token::StringLiteral(_) => "STRING",
-        Token::CliOption(_) => "OPTION",
-        Token::Program(_) => "PROGRAM",
-        Token::Variable(_) => "VARIABLE",
-        Token::Equals => "EQUALS",
-        Token::Redirect(_) => "REDIRECT",
-        Token::Async => "ASYNC",
+            _ if !arg.starts_with('-') => input_files.push(arg),
+            _ => usage_error(&format!("Unknown argument: {arg}")),
+        }
     }
-}
 
-//------------------------------------------------------------------------------
-// Main program
-//------------------------------------------------------------------------------
-fn main() {
     //
-    // Parse command-line arguments
+    // Diff compares two files instead of checking one
     //
-    let mut ignore_existing = false;
-    for arg in env::args().skip(1) {
-        if arg == "--ignore-existing" {
-            ignore_existing = true;
-        } else {
-            eprintln!("Unknown argument: {arg}");
-            std::process::exit(2);
-        }
+    if let Mode::Diff = mode {
+        let [old_file, new_file] = input_files.as_slice() else {
+            usage_error("Usage: croncheck diff [--dialect NAME] [--start YYYY-MM-DD] OLD NEW");
+        };
+        let now = start.map_or_else(|| Local::now().naive_local(), |d| d.and_time(NaiveTime::MIN));
+        std::process::exit(run_diff(old_file, new_file, dialect, now));
+    }
+    if input_files.len() > 1 {
+        usage_error(&format!("Unexpected argument: {}", input_files[1]));
     }
 
     //
-    // Read input from stdin
+    // Read input from the file argument, or stdin
     //
 
-    let mut buffer = String::new();
-    if io::stdin().read_to_string(&mut buffer).is_err() {
-        eprintln!("Failed to read stdin");
-        std::process::exit(1);
-    }
-    let source = buffer.trim();
-    if source.is_empty() {
+    let (file_name, buffer) = read_source(input_files.first());
+    let source = buffer.as_str();
+    if source.trim().is_empty() {
         eprintln!("Provide cron text via stdin");
         std::process::exit(1);
     }
@@ -330,19 +136,86 @@ fn main() {
     // Parse the input source
     //
 
-    match cron_lexer().parse(source) {
-        Ok(tokens) => {
+    let index = LineIndex::new(source);
+    let (tokens, crontab, mut diagnostics) = parse_source(source, dialect);
+    let mut missed_runs = false;
+
+    // only the check mode's stdout is free for a JSON or SARIF document
+    if !matches!(mode, Mode::Check) {
+        format = Format::Text;
+    }
+
+    match mode {
+        Mode::Timeline(days) => {
+            timeline::print_report(
+                &crontab.entries,
+                &TimelineOptions {
+                    start: start
+                        .unwrap_or_else(|| Local::now().date_naive())
+                        .and_time(NaiveTime::MIN),
+                    days,
+                    default_duration,
+                    durations,
+                    hotspot_threshold,
+                    format: timeline_format,
+                },
+            );
+        }
+        Mode::ToSystemd(dir) => {
+            match systemd::write_units(&crontab, &dir) {
+                Ok(written) => {
+                    for (path, units) in written {
+                        println!("{}.service", path.display());
+                        println!("{}.timer", path.display());
+                        for warning in units.warnings {
+                            eprintln!("{}: {warning}", units.name.yellow());
+                        }
+                    }
+                }
+                Err(e) => {
+                    eprintln!("Failed to write units to {}: {e}", dir.display());
+                    std::process::exit(1);
+                }
+            }
+        }
+        Mode::History(log) => {
+            let text = fs::read_to_string(&log).unwrap_or_else(|e| {
+                eprintln!("Failed to read {}: {e}", log.display());
+                std::process::exit(1);
+            });
+            let runs = history::parse_log(&text, Local::now().naive_local());
+            if history::print_report(&crontab.entries, &runs) {
+                missed_runs = true;
+            }
+        }
+        Mode::Diff => unreachable!("handled before reading input"),
+        Mode::Check => {
             let mut paths = Vec::new();
+            // lines already reported as invalid entries aren't checked further
+            let invalid_lines: Vec<usize> = crontab
+                .errors
+                .iter()
+                .map(|e| index.line(e.span.start))
+                .collect();
 
             //
             // Debug output of all tokens
             //
-            for token in tokens {
-                if let Token::Path(ref p) = token {
-                    paths.push(p.clone());
+            for (i, (token, span)) in tokens.iter().enumerate() {
+                // an assignment's value, such as `CRON_TZ=Europe/London`,
+                // isn't a file to look for
+                let assigned = i > 0 && tokens[i - 1].0 == Token::Equals;
+                if let Token::Path(p) = token
+                    && !assigned
+                    && !invalid_lines.contains(&index.line(span.start))
+                {
+                    paths.push((p.clone(), span.clone()));
+                }
+                if format != Format::Text {
+                    continue;
                 }
 
-                let tt_label = token_label(&token);
+                let tt_label = token_label(token);
                 let t = format!("{token:?}");
                 eprintln!(
                     "{:<7} {:>10}:{:<5} {:>32} {}",
@@ -353,10 +226,11 @@ fn main() {
                     "main()".yellow()
                 );
             }
+
             //
             // Output paths, filtering existing ones if requested
             //
-            for path in paths {
+            for (path, span) in paths {
                 let skip_exists_check = path.contains('`') 
                     || path.contains(':')
                     || path.contains('=')
@@ -364,20 +238,122 @@ fn main() {
                 if skip_exists_check {
                     continue;
                 }
-                if Path::new(&path).exists() {
-                    if ignore_existing {
-                        continue;
-                    }
+                let exists = Path::new(&path).exists();
+                if exists && ignore_existing {
+                    continue;
+                }
+
+                if format == Format::Text {
+                    println!("{path}");
+                } else if exists {
+                    diagnostics.push(Diagnostic::new(
+                        "path-reference",
+                        Severity::Note,
+                        &index,
+                        &span,
+                        format!("references {path}"),
+                    ));
+                } else {
+                    diagnostics.push(Diagnostic::new(
+                        "missing-path",
+                        Severity::Warning,
+                        &index,
+                        &span,
+                        format!("{path} does not exist"),
+                    ));
                 }
+            }
+        }
+    }
 
-                println!("{path}");
+    //
+    // Report diagnostics; any error makes the exit status 1, but only
+    // after every line has been checked
+    //
+    diagnostics.sort_by_key(|d| (d.line, d.column));
+    match format {
+        Format::Text => {
+            for diagnostic in &diagnostics {
+                eprintln!("{}", diagnostic.to_text(&file_name, source));
             }
         }
-        Err(errors) => {
-            for error in errors {
-                eprintln!("Lexer error: {error}");
+        _ => println!("{}", diagnostics::render(&diagnostics, &file_name, format)),
+    }
+    if missed_runs || diagnostics.iter().any(|d| d.severity == Severity::Error) {
+        std::process::exit(1);
+    }
+}
+
+/// Read the named file, or stdin, returning the name to report it by and
+/// its contents.
+fn read_source(file: Option<&String>) -> (String, String) {
+    let mut buffer = String::new();
+    match file {
+        Some(file) => {
+            buffer = fs::read_to_string(file).unwrap_or_else(|e| {
+                eprintln!("Failed to read {file}: {e}");
+                std::process::exit(1);
+            });
+            (file.clone(), buffer)
+        }
+        None => {
+            if io::stdin().read_to_string(&mut buffer).is_err() {
+                eprintln!("Failed to read stdin");
+                std::process::exit(1);
             }
-            std::process::exit(1);
+            ("stdin".to_string(), buffer)
         }
     }
 }
+
+/// Lex and parse a crontab, collecting lex and entry errors as diagnostics.
+fn parse_source(source: &str, dialect: Dialect) -> (Vec<(Token, Span)>, Crontab, Vec<Diagnostic>) {
+    let index = LineIndex::new(source);
+    let (tokens, lex_errors) = lexer::lex(source, dialect);
+    let mut diagnostics: Vec<Diagnostic> = lex_errors
+        .into_iter()
+        .map(|e| Diagnostic::new("lex-error", Severity::Error, &index, &e.span, e.message))
+        .collect();
+    let crontab = crontab::parse(source, &tokens, dialect);
+    diagnostics.extend(entry_diagnostics(&crontab, &index));
+    (tokens, crontab, diagnostics)
+}
+
+/// Print the semantic diff of two crontabs, returning the exit status:
+/// like diff(1), 0 when no job runs at different times and 1 otherwise.
+/// Lines that don't parse are reported and left out of the comparison.
+fn run_diff(old_file: &str, new_file: &str, dialect: Dialect, now: NaiveDateTime) -> i32 {
+    let (old_name, old_source) = read_source(Some(&old_file.to_string()));
+    let (new_name, new_source) = read_source(Some(&new_file.to_string()));
+    let (_, old, old_diagnostics) = parse_source(&old_source, dialect);
+    let (_, new, new_diagnostics) = parse_source(&new_source, dialect);
+    for diagnostic in &old_diagnostics {
+        eprintln!("{}", diagnostic.to_text(&old_name, &old_source));
+    }
+    for diagnostic in &new_diagnostics {
+        eprintln!("{}", diagnostic.to_text(&new_name, &new_source));
+    }
+
+    let changes = diff::diff(&old, &new);
+    diff::print_report(&changes, now);
+    i32::from(changes.iter().any(|change| change.is_semantic()))
+}
+
+/// Lines that look like entries but aren't valid ones.
+fn entry_diagnostics(crontab: &Crontab, index: &LineIndex) -> Vec<Diagnostic> {
+    crontab
+        .errors
+        .iter()
+        .map(|e| Diagnostic::new("invalid-entry", Severity::Error, index, &e.span, e.message.clone()))
+        .collect()
+}
+
+fn arg_value(args: &mut impl Iterator<Item = String>, flag: &str) -> String {
+    args.next()
+        .unwrap_or_else(|| usage_error(&format!("{flag} expects a value")))
+}
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}");
+    std::process::exit(2);
+}
@@ -0,0 +1,771 @@
+use chrono::{Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Weekday};
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+const DOW_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+// How far ahead next_after() searches before giving up on a schedule that
+// can never fire (e.g. `0 0 31 2 *`).
+const SEARCH_YEARS: i32 = 30;
+
+//------------------------------------------------------------------------------
+// Dialects
+//------------------------------------------------------------------------------
+
+/// Which cron implementation's schedule syntax and semantics to follow.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Dialect {
+    /// Vixie/ISC cron, the classic five fields
+    #[default]
+    Vixie,
+    /// Kubernetes CronJob: five fields, `?` allowed in the day fields
+    Kubernetes,
+    /// Jenkins: five fields plus `H` hashed values
+    Jenkins,
+    /// Quartz: seconds, an optional year, weekdays 1-7 and `?` `L` `W` `#`
+    Quartz,
+    /// Spring `CronExpression`: seconds, weekdays 0-7 and `?` `L` `W` `#`
+    Spring,
+}
+
+impl Dialect {
+    pub fn from_name(name: &str) -> Option<Dialect> {
+        match name {
+            "vixie" => Some(Dialect::Vixie),
+            "kubernetes" | "k8s" => Some(Dialect::Kubernetes),
+            "jenkins" => Some(Dialect::Jenkins),
+            "quartz" => Some(Dialect::Quartz),
+            "spring" => Some(Dialect::Spring),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Dialect::Vixie => "vixie",
+            Dialect::Kubernetes => "kubernetes",
+            Dialect::Jenkins => "jenkins",
+            Dialect::Quartz => "quartz",
+            Dialect::Spring => "spring",
+        }
+    }
+
+    /// Number of schedule fields, not counting Quartz's optional year.
+    pub fn field_count(&self) -> usize {
+        if self.has_seconds() { 6 } else { 5 }
+    }
+
+    pub fn has_seconds(&self) -> bool {
+        matches!(self, Dialect::Quartz | Dialect::Spring)
+    }
+
+    pub fn has_year(&self) -> bool {
+        *self == Dialect::Quartz
+    }
+
+    /// `L`, `W` and `#`
+    pub fn has_day_rules(&self) -> bool {
+        matches!(self, Dialect::Quartz | Dialect::Spring)
+    }
+
+    fn has_question_mark(&self) -> bool {
+        !matches!(self, Dialect::Vixie | Dialect::Jenkins)
+    }
+
+    /// Vixie cron and the robfig/cron library behind Kubernetes fire when
+    /// either restricted day field matches; Jenkins, Quartz and Spring need
+    /// both to match.
+    fn ors_day_fields(&self) -> bool {
+        matches!(self, Dialect::Vixie | Dialect::Kubernetes)
+    }
+}
+
+//------------------------------------------------------------------------------
+// Fields
+//------------------------------------------------------------------------------
+
+/// A schedule field that failed to parse, `field` is its 0-based position.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: usize,
+    pub message: String,
+}
+
+/// The set of values one cron field matches, as a bitmask.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldSet {
+    bits: u64,
+}
+
+impl FieldSet {
+    fn from_values(values: &[u32]) -> FieldSet {
+        FieldSet {
+            bits: values.iter().fold(0, |bits, v| bits | 1 << v),
+        }
+    }
+
+    pub fn contains(&self, value: u32) -> bool {
+        value < 64 && self.bits & (1 << value) != 0
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = u32> + '_ {
+        (0..64).filter(|v| self.contains(*v))
+    }
+
+    /// The smallest value in the set that is `>= value`.
+    fn next_from(&self, value: u32) -> Option<u32> {
+        (value..64).find(|v| self.contains(*v))
+    }
+}
+
+/// Days that depend on the month rather than being fixed values: the `L`,
+/// `W` and `#` extensions. Weekdays are 0 = Sunday.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum DayRule {
+    /// `L` or `L-3` in day of month: the last day minus an offset
+    LastDay(u32),
+    /// `LW`: the last weekday (Mon-Fri) of the month
+    LastWeekday,
+    /// `15W`: the weekday nearest the 15th, without leaving the month
+    NearestWeekday(u32),
+    /// `5L` in day of week: the last Friday of the month
+    LastOf(u32),
+    /// `6#3` in day of week: the third Friday (Quartz) of the month
+    Nth(u32, u32),
+}
+
+impl DayRule {
+    fn is_day_of_week(&self) -> bool {
+        matches!(self, DayRule::LastOf(_) | DayRule::Nth(_, _))
+    }
+
+    fn matches(&self, date: NaiveDate) -> bool {
+        let last = last_day_of_month(date);
+        let weekday = date.weekday().num_days_from_sunday();
+        match *self {
+            DayRule::LastDay(offset) => last.checked_sub(offset) == Some(date.day()),
+            DayRule::LastWeekday => nearest_weekday(date, last) == Some(date.day()),
+            DayRule::NearestWeekday(day) => nearest_weekday(date, day) == Some(date.day()),
+            DayRule::LastOf(dow) => weekday == dow && date.day() + 7 > last,
+            DayRule::Nth(dow, n) => weekday == dow && (date.day() - 1) / 7 + 1 == n,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Slot {
+    Second,
+    Minute,
+    Hour,
+    DayOfMonth,
+    Month,
+    DayOfWeek,
+    Year,
+}
+
+impl Slot {
+    fn name(&self) -> &'static str {
+        match self {
+            Slot::Second => "second",
+            Slot::Minute => "minute",
+            Slot::Hour => "hour",
+            Slot::DayOfMonth => "day of month",
+            Slot::Month => "month",
+            Slot::DayOfWeek => "day of week",
+            Slot::Year => "year",
+        }
+    }
+
+    /// The range as written: Quartz numbers weekdays 1-7 from Sunday, the
+    /// others 0-7 with 7 as a second Sunday.
+    fn range(&self, dialect: Dialect) -> (u32, u32) {
+        match self {
+            Slot::Second | Slot::Minute => (0, 59),
+            Slot::Hour => (0, 23),
+            Slot::DayOfMonth => (1, 31),
+            Slot::Month => (1, 12),
+            Slot::DayOfWeek if dialect == Dialect::Quartz => (1, 7),
+            Slot::DayOfWeek => (0, 7),
+            Slot::Year => (1970, 2099),
+        }
+    }
+
+    fn names(&self) -> &'static [&'static str] {
+        match self {
+            Slot::Month => &MONTH_NAMES,
+            Slot::DayOfWeek => &DOW_NAMES,
+            _ => &[],
+        }
+    }
+}
+
+#[derive(Default)]
+struct ParsedField {
+    values: Vec<u32>,
+    rules: Vec<DayRule>,
+    /// starts with `*` or is `?`
+    unrestricted: bool,
+    question_mark: bool,
+}
+
+//------------------------------------------------------------------------------
+// Schedule
+//------------------------------------------------------------------------------
+
+/// A parsed schedule: the time fields (or an `@` nickname) of one entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    /// `{0}` for dialects without a seconds field
+    pub seconds: FieldSet,
+    pub minutes: FieldSet,
+    pub hours: FieldSet,
+    pub days_of_month: FieldSet,
+    pub months: FieldSet,
+    /// 0 = Sunday whatever the dialect's own numbering
+    pub days_of_week: FieldSet,
+    /// Quartz's optional year field, `None` when absent or `*`
+    pub years: Option<Vec<u32>>,
+    pub day_rules: Vec<DayRule>,
+    pub dom_restricted: bool,
+    pub dow_restricted: bool,
+    /// Vixie cron ORs day-of-month and day-of-week when both are restricted,
+    /// i.e. when neither field starts with `*`; otherwise both must match
+    pub days_ored: bool,
+    /// `@reboot` jobs have no calendar firings
+    pub reboot: bool,
+}
+
+impl Schedule {
+    /// Parse the whitespace separated time fields of a crontab entry. `job`
+    /// seeds Jenkins' `H` so every job gets its own, stable, spread.
+    pub fn parse(fields: &[&str], dialect: Dialect, job: &str) -> Result<Schedule, FieldError> {
+        let count = dialect.field_count();
+        if fields.len() != count && !(dialect.has_year() && fields.len() == count + 1) {
+            return Err(FieldError {
+                field: fields.len().min(count) - 1,
+                message: format!(
+                    "expected {count} schedule fields for {} cron, found {}",
+                    dialect.name(),
+                    fields.len()
+                ),
+            });
+        }
+
+        let mut slots = vec![Slot::Minute, Slot::Hour, Slot::DayOfMonth, Slot::Month, Slot::DayOfWeek];
+        if dialect.has_seconds() {
+            slots.insert(0, Slot::Second);
+        }
+        slots.push(Slot::Year);
+
+        let mut schedule = Schedule {
+            seconds: FieldSet::from_values(&[0]),
+            minutes: FieldSet { bits: 0 },
+            hours: FieldSet { bits: 0 },
+            days_of_month: FieldSet { bits: 0 },
+            months: FieldSet { bits: 0 },
+            days_of_week: FieldSet { bits: 0 },
+            years: None,
+            day_rules: Vec::new(),
+            dom_restricted: false,
+            dow_restricted: false,
+            days_ored: false,
+            reboot: false,
+        };
+        let mut question_marks = 0;
+
+        for (idx, (text, slot)) in fields.iter().zip(slots.iter()).enumerate() {
+            let seed = format!("{job}#{idx}");
+            let parsed = parse_field(text, *slot, dialect, &seed)
+                .map_err(|message| FieldError { field: idx, message })?;
+            if parsed.question_mark {
+                question_marks += 1;
+            }
+            // years don't fit in a FieldSet
+            if *slot == Slot::Year {
+                if !parsed.unrestricted {
+                    schedule.years = Some(parsed.values);
+                }
+                continue;
+            }
+
+            let set = FieldSet::from_values(&parsed.values);
+            match slot {
+                Slot::Second => schedule.seconds = set,
+                Slot::Minute => schedule.minutes = set,
+                Slot::Hour => schedule.hours = set,
+                Slot::DayOfMonth => {
+                    schedule.days_of_month = set;
+                    schedule.dom_restricted = !parsed.unrestricted;
+                }
+                Slot::Month => schedule.months = set,
+                Slot::DayOfWeek => {
+                    schedule.days_of_week = set;
+                    schedule.dow_restricted = !parsed.unrestricted;
+                }
+                Slot::Year => unreachable!(),
+            }
+            schedule.day_rules.extend(parsed.rules);
+        }
+
+        if dialect == Dialect::Quartz && question_marks != 1 {
+            return Err(FieldError {
+                field: count - 1,
+                message: "quartz needs `?` in exactly one of day of month and day of week"
+                    .to_string(),
+            });
+        }
+        schedule.days_ored =
+            dialect.ors_day_fields() && schedule.dom_restricted && schedule.dow_restricted;
+
+        Ok(schedule)
+    }
+
+    /// Expand an `@` nickname such as `@daily`, `None` if the dialect has
+    /// no such nickname.
+    pub fn from_nickname(nickname: &str, dialect: Dialect, job: &str) -> Option<Schedule> {
+        // Jenkins spreads its nicknames over the period with H
+        let (minute, hour) = match dialect {
+            Dialect::Jenkins => ("H", "H"),
+            _ => ("0", "0"),
+        };
+        let fields = match (nickname, dialect) {
+            (_, Dialect::Quartz) => return None,
+            ("@yearly" | "@annually", _) => [minute, hour, "1", "1", "*"],
+            ("@monthly", _) => [minute, hour, "1", "*", "*"],
+            ("@weekly", _) => [minute, hour, "*", "*", "0"],
+            ("@midnight", Dialect::Jenkins) => [minute, "H(0-2)", "*", "*", "*"],
+            ("@daily" | "@midnight", _) => [minute, hour, "*", "*", "*"],
+            ("@hourly", _) => [minute, "*", "*", "*", "*"],
+            ("@reboot", Dialect::Vixie) => {
+                let mut schedule = Schedule::parse(&["*"; 5], dialect, job).ok()?;
+                schedule.reboot = true;
+                return Some(schedule);
+            }
+            _ => return None,
+        };
+        if dialect.has_seconds() {
+            let [minute, hour, dom, month, dow] = fields;
+            return Schedule::parse(&["0", minute, hour, dom, month, dow], dialect, job).ok();
+        }
+        Schedule::parse(&fields, dialect, job).ok()
+    }
+
+    /// Whether both schedules fire at exactly the same times, however they
+    /// are written: `0 */6 * * *` and `0 0,6,12,18 * * *` are equivalent.
+    pub fn equivalent(&self, other: &Schedule) -> bool {
+        self.reboot == other.reboot
+            && self.seconds == other.seconds
+            && self.minutes == other.minutes
+            && self.hours == other.hours
+            && self.months == other.months
+            && self.years == other.years
+            && self.day_key() == other.day_key()
+    }
+
+    /// The day fields reduced to what they match: ORed fields where one of
+    /// them matches every day are the same as no day restriction at all.
+    fn day_key(&self) -> (bool, FieldSet, FieldSet, Vec<DayRule>) {
+        let every_dom = FieldSet::from_values(&(1..=31).collect::<Vec<_>>());
+        let every_dow = FieldSet::from_values(&(0..=6).collect::<Vec<_>>());
+        if self.days_ored && (self.days_of_month == every_dom || self.days_of_week == every_dow) {
+            return (false, every_dom, every_dow, Vec::new());
+        }
+        let mut rules = self.day_rules.clone();
+        rules.sort_unstable();
+        rules.dedup();
+        (self.days_ored, self.days_of_month, self.days_of_week, rules)
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !self.months.contains(date.month()) {
+            return false;
+        }
+        if let Some(years) = &self.years
+            && !years.contains(&(date.year() as u32))
+        {
+            return false;
+        }
+
+        let rule_matches = |day_of_week: bool| {
+            self.day_rules
+                .iter()
+                .any(|rule| rule.is_day_of_week() == day_of_week && rule.matches(date))
+        };
+        let dom = self.days_of_month.contains(date.day()) || rule_matches(false);
+        let dow = self
+            .days_of_week
+            .contains(date.weekday().num_days_from_sunday())
+            || rule_matches(true);
+        if self.days_ored {
+            dom || dow
+        } else {
+            dom && dow
+        }
+    }
+
+    /// The first firing strictly after `time`, skipping whole days, hours
+    /// and minutes that can't match instead of stepping second by second.
+    pub fn next_after(&self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        if self.reboot {
+            return None;
+        }
+
+        let mut t = time.with_nanosecond(0)? + Duration::seconds(1);
+        let give_up = time.year() + SEARCH_YEARS;
+
+        while t.year() <= give_up {
+            if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_time(NaiveTime::MIN);
+                continue;
+            }
+            let Some(hour) = self.hours.next_from(t.hour()) else {
+                t = t.date().succ_opt()?.and_time(NaiveTime::MIN);
+                continue;
+            };
+            if hour != t.hour() {
+                t = t.date().and_hms_opt(hour, 0, 0)?;
+                continue;
+            }
+            let Some(minute) = self.minutes.next_from(t.minute()) else {
+                t = t.with_minute(0)?.with_second(0)? + Duration::hours(1);
+                continue;
+            };
+            if minute != t.minute() {
+                t = t.with_minute(minute)?.with_second(0)?;
+                continue;
+            }
+            let Some(second) = self.seconds.next_from(t.second()) else {
+                t = t.with_second(0)? + Duration::minutes(1);
+                continue;
+            };
+            return t.with_second(second);
+        }
+        None
+    }
+
+    /// All firings in the half-open interval `[start, end)`.
+    pub fn firings(&self, start: NaiveDateTime, end: NaiveDateTime) -> Vec<NaiveDateTime> {
+        let mut out = Vec::new();
+        let mut t = start - Duration::seconds(1);
+        while let Some(next) = self.next_after(t) {
+            if next >= end {
+                break;
+            }
+            out.push(next);
+            t = next;
+        }
+        out
+    }
+}
+
+//------------------------------------------------------------------------------
+// Field parsing
+//------------------------------------------------------------------------------
+
+fn parse_field(text: &str, slot: Slot, dialect: Dialect, seed: &str) -> Result<ParsedField, String> {
+    let name = slot.name();
+    let (min, max) = slot.range(dialect);
+    let mut parsed = ParsedField {
+        unrestricted: text.starts_with('*') || text == "?",
+        ..ParsedField::default()
+    };
+
+    for item in text.split(',') {
+        if item == "?" {
+            let day_field = matches!(slot, Slot::DayOfMonth | Slot::DayOfWeek);
+            if !dialect.has_question_mark() || !day_field || text != "?" {
+                return Err(format!("`?` is not valid in the {name} field for {} cron", dialect.name()));
+            }
+            parsed.question_mark = true;
+            parsed.values.extend(min..=max);
+            continue;
+        }
+        // on its own in day of week, L is the last day of the week, so every
+        // Saturday rather than a day that depends on the month
+        if slot == Slot::DayOfWeek && item.eq_ignore_ascii_case("L") {
+            if !dialect.has_day_rules() {
+                return Err(format!("`{item}` needs the quartz or spring dialect"));
+            }
+            parsed.values.push(parse_value("SAT", slot, dialect)?);
+            continue;
+        }
+        if let Some(rule) = parse_day_rule(item, slot, dialect)? {
+            parsed.rules.push(rule);
+            continue;
+        }
+
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step: u32 = step
+                    .parse()
+                    .map_err(|_| format!("invalid step `{step}` in {name} field"))?;
+                if step == 0 {
+                    return Err(format!("step must be positive in {name} field"));
+                }
+                (range, Some(step))
+            }
+            None => (item, None),
+        };
+
+        let (low, high) = if range == "*" {
+            (min, max)
+        } else if range.starts_with('H') {
+            parse_hash(range, step, slot, dialect, seed)?
+        } else if let Some((low, high)) = range.split_once('-') {
+            (parse_value(low, slot, dialect)?, parse_value(high, slot, dialect)?)
+        } else {
+            let low = parse_value(range, slot, dialect)?;
+            // Vixie extension: `5/10` means `5-max/10`
+            let high = if step.is_some() { max } else { low };
+            (low, high)
+        };
+
+        if low > high {
+            return Err(format!("range `{range}` is backwards in {name} field"));
+        }
+        parsed
+            .values
+            .extend((low..=high).step_by(step.unwrap_or(1) as usize));
+    }
+
+    if slot == Slot::DayOfWeek {
+        for value in parsed.values.iter_mut() {
+            *value = sunday_zero(*value, dialect);
+        }
+    }
+    parsed.values.sort_unstable();
+    parsed.values.dedup();
+    Ok(parsed)
+}
+
+fn parse_value(text: &str, slot: Slot, dialect: Dialect) -> Result<u32, String> {
+    let (min, max) = slot.range(dialect);
+    let upper = text.to_ascii_uppercase();
+    if let Some(idx) = slot.names().iter().position(|name| *name == upper) {
+        // month names start at 1 (JAN), weekday names at the dialect's SUN
+        return Ok(idx as u32 + min);
+    }
+    let value: u32 = text
+        .parse()
+        .map_err(|_| format!("invalid value `{text}` in {} field", slot.name()))?;
+    if value < min || value > max {
+        return Err(format!(
+            "{value} is out of range {min}-{max} in {} field",
+            slot.name()
+        ));
+    }
+    Ok(value)
+}
+
+/// Map a weekday as written onto 0 = Sunday.
+fn sunday_zero(value: u32, dialect: Dialect) -> u32 {
+    if dialect == Dialect::Quartz { value - 1 } else { value % 7 }
+}
+
+/// `L`, `L-3`, `LW` and `15W` in day of month, `5L` and `6#3` in day of
+/// week. `None` means the item is an ordinary value.
+fn parse_day_rule(item: &str, slot: Slot, dialect: Dialect) -> Result<Option<DayRule>, String> {
+    let upper = item.to_ascii_uppercase();
+    let before = |suffix: char| upper.strip_suffix(suffix).filter(|rest| !rest.is_empty());
+
+    let rule = match slot {
+        Slot::DayOfMonth if upper == "L" => DayRule::LastDay(0),
+        Slot::DayOfMonth if upper == "LW" => DayRule::LastWeekday,
+        Slot::DayOfMonth if upper.starts_with("L-") => {
+            let offset: u32 = upper[2..]
+                .parse()
+                .ok()
+                .filter(|offset| *offset < 31)
+                .ok_or_else(|| format!("invalid offset in `{item}`, expected L-0 to L-30"))?;
+            DayRule::LastDay(offset)
+        }
+        Slot::DayOfMonth if before('W').is_some() => {
+            DayRule::NearestWeekday(parse_value(before('W').unwrap_or_default(), slot, dialect)?)
+        }
+        Slot::DayOfWeek if before('L').is_some() => {
+            let day = parse_value(before('L').unwrap_or_default(), slot, dialect)?;
+            DayRule::LastOf(sunday_zero(day, dialect))
+        }
+        Slot::DayOfWeek if upper.contains('#') => {
+            let (day, n) = upper.split_once('#').unwrap_or_default();
+            let day = parse_value(day, slot, dialect)?;
+            let n: u32 = n
+                .parse()
+                .ok()
+                .filter(|n| (1..=5).contains(n))
+                .ok_or_else(|| format!("invalid week in `{item}`, expected #1 to #5"))?;
+            DayRule::Nth(sunday_zero(day, dialect), n)
+        }
+        _ => return Ok(None),
+    };
+
+    if !dialect.has_day_rules() {
+        return Err(format!("`{item}` needs the quartz or spring dialect"));
+    }
+    Ok(Some(rule))
+}
+
+/// Jenkins' `H`, `H/15`, `H(0-29)` and `H(0-29)/10`: a value hashed from
+/// the job so jobs sharing a schedule don't all start at once.
+fn parse_hash(
+    range: &str,
+    step: Option<u32>,
+    slot: Slot,
+    dialect: Dialect,
+    seed: &str,
+) -> Result<(u32, u32), String> {
+    if dialect != Dialect::Jenkins {
+        return Err(format!("`{range}` needs the jenkins dialect"));
+    }
+    let (low, high) = match range.strip_prefix("H(").and_then(|r| r.strip_suffix(')')) {
+        Some(inner) => {
+            let (low, high) = inner
+                .split_once('-')
+                .ok_or_else(|| format!("expected H(low-high), found `{range}`"))?;
+            (parse_value(low, slot, dialect)?, parse_value(high, slot, dialect)?)
+        }
+        // H in day of week must not pick the second Sunday
+        None if range == "H" && slot == Slot::DayOfWeek => (0, 6),
+        None if range == "H" => slot.range(dialect),
+        None => return Err(format!("expected H or H(low-high), found `{range}`")),
+    };
+    if low > high {
+        return Err(format!("range `{range}` is backwards in {} field", slot.name()));
+    }
+
+    let hash = fnv1a(seed);
+    Ok(match step {
+        // H/15: a hashed start within the first step, then every step
+        Some(step) => {
+            let offset = hash % u64::from(step.min(high - low + 1));
+            (low + offset as u32, high)
+        }
+        None => {
+            let value = low + (hash % u64::from(high - low + 1)) as u32;
+            (value, value)
+        }
+    })
+}
+
+fn fnv1a(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x100000001b3)
+    })
+}
+
+//------------------------------------------------------------------------------
+// Calendar helpers
+//------------------------------------------------------------------------------
+
+fn last_day_of_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first| first.pred_opt())
+        .map_or(31, |last| last.day())
+}
+
+/// Quartz's `W`: the weekday nearest `day` in `date`'s month. It never
+/// leaves the month, so a Saturday the 1st moves on to Monday the 3rd.
+fn nearest_weekday(date: NaiveDate, day: u32) -> Option<u32> {
+    let target = date.with_day(day)?;
+    let last = last_day_of_month(date);
+    Some(match target.weekday() {
+        Weekday::Sat if day == 1 => 3,
+        Weekday::Sat => day - 1,
+        Weekday::Sun if day == last => day - 2,
+        Weekday::Sun => day + 1,
+        _ => day,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").expect("a valid time")
+    }
+
+    fn schedule(text: &str, dialect: Dialect) -> Schedule {
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        Schedule::parse(&fields, dialect, "job").expect("a valid schedule")
+    }
+
+    /// Every firing in October 2026, as dates.
+    fn october(text: &str, dialect: Dialect) -> Vec<String> {
+        schedule(text, dialect)
+            .firings(at("2026-10-01 00:00:00"), at("2026-11-01 00:00:00"))
+            .iter()
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn next_after_follows_day_rules() {
+        let cases = [
+            // last day of the month, and three days before it
+            ("0 0 12 L * ?", "2026-02-10 00:00:00", "2026-02-28 12:00:00"),
+            ("0 0 12 L-3 * ?", "2026-02-10 00:00:00", "2026-02-25 12:00:00"),
+            // the 31st is a Saturday, so the last weekday is Friday the 30th
+            ("0 0 12 LW * ?", "2026-10-01 00:00:00", "2026-10-30 12:00:00"),
+            // Sunday the 15th moves to Monday, Saturday the 1st to Monday the 3rd
+            ("0 0 12 15W * ?", "2026-11-01 00:00:00", "2026-11-16 12:00:00"),
+            ("0 0 12 1W * ?", "2026-07-31 00:00:00", "2026-08-03 12:00:00"),
+            // Quartz numbers Sunday 1, so 6 is Friday and 5 Thursday
+            ("0 0 12 ? * 6#3", "2026-10-01 00:00:00", "2026-10-16 12:00:00"),
+            ("0 0 12 ? * 5L", "2026-10-01 00:00:00", "2026-10-29 12:00:00"),
+            ("0 0 12 ? * L", "2026-10-01 00:00:00", "2026-10-03 12:00:00"),
+        ];
+        for (text, after, expected) in cases {
+            assert_eq!(
+                schedule(text, Dialect::Quartz).next_after(at(after)),
+                Some(at(expected)),
+                "{text} after {after}"
+            );
+        }
+    }
+
+    #[test]
+    fn bare_l_in_day_of_week_is_every_saturday() {
+        let saturdays = ["2026-10-03", "2026-10-10", "2026-10-17", "2026-10-24", "2026-10-31"];
+        assert_eq!(october("0 0 12 ? * L", Dialect::Quartz), saturdays);
+        assert_eq!(october("0 0 12 * * L", Dialect::Spring), saturdays);
+    }
+
+    #[test]
+    fn question_mark_leaves_the_day_to_the_other_field() {
+        // Sunday the 18th, so the next Monday is the 19th
+        let schedule = schedule("0 12 ? * MON", Dialect::Kubernetes);
+        assert_eq!(
+            schedule.next_after(at("2026-10-18 00:00:00")),
+            Some(at("2026-10-19 12:00:00"))
+        );
+        assert!(Schedule::parse(&["0", "12", "?", "*", "MON"], Dialect::Vixie, "job").is_err());
+    }
+
+    #[test]
+    fn jenkins_hash_is_stable_and_within_its_range() {
+        let daily = schedule("H H(0-2) * * *", Dialect::Jenkins);
+        assert_eq!(daily, schedule("H H(0-2) * * *", Dialect::Jenkins));
+
+        let firings = daily.firings(at("2026-10-01 00:00:00"), at("2026-10-08 00:00:00"));
+        assert_eq!(firings.len(), 7);
+        let first = firings[0];
+        assert!(first.hour() <= 2);
+        for firing in &firings {
+            assert_eq!((firing.hour(), firing.minute()), (first.hour(), first.minute()));
+        }
+
+        // H/15 starts somewhere in the first quarter hour, then every 15
+        let quarterly = schedule("H/15 * * * *", Dialect::Jenkins);
+        let minutes: Vec<u32> = quarterly.minutes.values().collect();
+        assert_eq!(minutes.len(), 4);
+        assert!(minutes[0] < 15);
+        assert!(minutes.windows(2).all(|pair| pair[1] - pair[0] == 15));
+    }
+}
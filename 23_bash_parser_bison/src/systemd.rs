@@ -0,0 +1,487 @@
+use crate::crontab::{Crontab, Entry};
+use crate::lexer::Token;
+use crate::schedule::{DayRule, FieldSet};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+
+/// A `.service`/`.timer` pair translated from one crontab entry, plus the
+/// constructs that couldn't be carried over faithfully.
+pub struct Units {
+    pub name: String,
+    pub service: String,
+    pub timer: String,
+    pub warnings: Vec<String>,
+}
+
+/// Translate every entry and write the unit files into `dir`, returning
+/// the translations so the caller can report paths and warnings.
+pub fn write_units(crontab: &Crontab, dir: &Path) -> io::Result<Vec<(PathBuf, Units)>> {
+    fs::create_dir_all(dir)?;
+    let mut written = Vec::new();
+    for entry in &crontab.entries {
+        let units = translate(crontab, entry);
+        fs::write(dir.join(format!("{}.service", units.name)), &units.service)?;
+        fs::write(dir.join(format!("{}.timer", units.name)), &units.timer)?;
+        written.push((dir.join(&units.name), units));
+    }
+    Ok(written)
+}
+
+pub fn translate(crontab: &Crontab, entry: &Entry) -> Units {
+    let mut warnings = Vec::new();
+    let name = unit_name(entry);
+
+    //
+    // Environment assignments
+    //
+    let mut shell = "/bin/sh".to_string();
+    let mut timezone = None;
+    let mut environment = Vec::new();
+    for assignment in crontab.environment_for(entry) {
+        match assignment.name.as_str() {
+            "MAILTO" | "MAILFROM" => warnings.push(format!(
+                "{}={} has no systemd equivalent, output goes to the journal instead",
+                assignment.name, assignment.value
+            )),
+            "CRON_TZ" => timezone = Some(assignment.value.clone()),
+            "SHELL" => shell = assignment.value.clone(),
+            _ => {}
+        }
+        if !matches!(assignment.name.as_str(), "CRON_TZ" | "MAILTO" | "MAILFROM") {
+            environment.push(format!(
+                "Environment=\"{}={}\"",
+                assignment.name,
+                escape(&assignment.value)
+            ));
+        }
+    }
+
+    //
+    // Trailing redirects become StandardOutput=/StandardError=
+    //
+    let (command, output) = split_redirects(entry, &mut warnings);
+    if command.contains('%') {
+        warnings.push(
+            "`%` in a cron command starts stdin data, it is passed through literally".to_string(),
+        );
+    }
+
+    let calendars = on_calendar(entry, &mut warnings);
+    let mut service = header(entry, &warnings);
+    service.push_str(&format!(
+        "[Unit]\nDescription=cron line {}: {}\n\n[Service]\nType=oneshot\n",
+        entry.line,
+        escape_specifiers(&command)
+    ));
+    for line in environment {
+        service.push_str(&line);
+        service.push('\n');
+    }
+    service.push_str(&format!(
+        "ExecStart={} -c \"{}\"\n",
+        shell,
+        escape(&command).replace('$', "$$")
+    ));
+    for line in output {
+        service.push_str(&line);
+        service.push('\n');
+    }
+
+    //
+    // Schedule
+    //
+    let mut timer = header(entry, &[]);
+    timer.push_str(&format!(
+        "[Unit]\nDescription=Timer for cron line {}\n\n[Timer]\n",
+        entry.line
+    ));
+    if entry.schedule.reboot {
+        // cron runs @reboot jobs when the daemon starts, shortly after boot
+        timer.push_str("OnBootSec=1min\n");
+    } else {
+        for calendar in calendars {
+            match &timezone {
+                Some(tz) => timer.push_str(&format!("OnCalendar={calendar} {tz}\n")),
+                None => timer.push_str(&format!("OnCalendar={calendar}\n")),
+            }
+        }
+    }
+    timer.push_str("\n[Install]\nWantedBy=timers.target\n");
+
+    Units {
+        name,
+        service,
+        timer,
+        warnings,
+    }
+}
+
+fn header(entry: &Entry, warnings: &[String]) -> String {
+    let mut out = format!(
+        "# Generated by croncheck from crontab line {}:\n#   {} {}\n",
+        entry.line, entry.schedule_text, entry.command
+    );
+    for warning in warnings {
+        out.push_str(&format!("# WARNING: {warning}\n"));
+    }
+    out.push('\n');
+    out
+}
+
+/// `cron-<line>-<program>`, e.g. `cron-4-backup.sh`.
+fn unit_name(entry: &Entry) -> String {
+    let program = entry
+        .command_tokens
+        .iter()
+        .find_map(|(token, _)| match token {
+            Token::Path(p) | Token::Program(p) => p.rsplit('/').next(),
+            _ => None,
+        })
+        .unwrap_or("job");
+    let slug: String = program
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-') { c } else { '-' })
+        .collect();
+    format!("cron-{}-{}", entry.line, slug.trim_matches('-'))
+}
+
+//------------------------------------------------------------------------------
+// Redirects
+//------------------------------------------------------------------------------
+
+/// Strip redirects from the end of the command and express them as unit
+/// settings. Redirects in the middle of a pipeline stay in the shell command.
+fn split_redirects(entry: &Entry, warnings: &mut Vec<String>) -> (String, Vec<String>) {
+    let tokens = &entry.command_tokens;
+    let mut cut = tokens.len();
+    let mut redirects: Vec<(String, Option<String>)> = Vec::new();
+
+    loop {
+        match &tokens[..cut] {
+            [.., (Token::Redirect(op), _), (Token::Async, _), (Token::Int(1), _)] if op == "2>" => {
+                redirects.push((op.clone(), None));
+                cut -= 3;
+            }
+            [.., (Token::Redirect(op), _), (Token::Path(target), _)]
+            | [.., (Token::Redirect(op), _), (Token::Program(target), _)]
+            | [.., (Token::Redirect(op), _), (Token::StringLiteral(target), _)] => {
+                redirects.push((op.clone(), Some(target.clone())));
+                cut -= 2;
+            }
+            _ => break,
+        }
+    }
+    // nothing but redirects isn't a command worth splitting
+    if cut == 0 {
+        return (entry.command.clone(), Vec::new());
+    }
+    redirects.reverse();
+
+    let mut stdout: Option<String> = None;
+    let mut stderr: Option<String> = None;
+    let mut unmapped = false;
+    for (op, target) in redirects {
+        let fd = op.trim_end_matches('>');
+        let append = op.ends_with(">>");
+        let setting = match target {
+            // 2>&1 duplicates whatever stdout is at this point
+            None if stdout.is_some() => Some("inherit".to_string()),
+            None => Some("journal".to_string()),
+            Some(target) => output_target(&target, append),
+        };
+        let Some(setting) = setting else {
+            warnings.push(format!("redirect `{op}` to a relative path can't be expressed in a unit"));
+            unmapped = true;
+            continue;
+        };
+        match fd {
+            "" | "1" => stdout = Some(setting),
+            "2" => stderr = Some(setting),
+            _ => {
+                warnings.push(format!("redirect of file descriptor {fd} has no unit setting"));
+                unmapped = true;
+            }
+        }
+    }
+
+    // keep the whole command verbatim if any redirect couldn't be mapped
+    if unmapped {
+        return (entry.command.clone(), Vec::new());
+    }
+
+    let end = tokens[cut - 1].1.end - entry.command_span.start;
+    let mut settings = Vec::new();
+    if let Some(stdout) = stdout {
+        settings.push(format!("StandardOutput={stdout}"));
+    }
+    if let Some(stderr) = stderr {
+        settings.push(format!("StandardError={stderr}"));
+    }
+    (entry.command[..end].trim_end().to_string(), settings)
+}
+
+fn output_target(path: &str, append: bool) -> Option<String> {
+    if path == "/dev/null" {
+        return Some("null".to_string());
+    }
+    let path = if let Some(rest) = path.strip_prefix('~') {
+        format!("%h{rest}")
+    } else if path.starts_with('/') {
+        escape_specifiers(path)
+    } else {
+        return None;
+    };
+    // systemd's `file:` neither truncates nor appends, `truncate:` matches `>`
+    Some(if append { format!("append:{path}") } else { format!("truncate:{path}") })
+}
+
+//------------------------------------------------------------------------------
+// OnCalendar=
+//------------------------------------------------------------------------------
+
+/// Calendar expressions for the entry. Vixie cron fires when *either*
+/// restricted day field matches, while systemd ANDs weekday and date, so
+/// that case needs one expression per day field. `L` and `#` map onto
+/// systemd's `~` (days from the end) and day ranges; `W` has no equivalent.
+fn on_calendar(entry: &Entry, warnings: &mut Vec<String>) -> Vec<String> {
+    let schedule = &entry.schedule;
+    let time = format!(
+        "{}:{}:{}",
+        calendar_list(&schedule.hours, 0, 23),
+        calendar_list(&schedule.minutes, 0, 59),
+        calendar_list(&schedule.seconds, 0, 59)
+    );
+    let year = match &schedule.years {
+        Some(years) => runs(years)
+            .iter()
+            .map(|&(low, high)| if low == high { low.to_string() } else { format!("{low}..{high}") })
+            .collect::<Vec<_>>()
+            .join(","),
+        None => "*".to_string(),
+    };
+    let month = calendar_list(&schedule.months, 1, 12);
+
+    // (weekday prefix, date after the month) for each day field
+    let mut by_date: Vec<(Option<String>, String)> = Vec::new();
+    let mut by_weekday: Vec<(Option<String>, String)> = Vec::new();
+    if schedule.days_of_month.values().next().is_some() {
+        let dom = calendar_list(&schedule.days_of_month, 1, 31);
+        by_date.push((None, format!("-{dom}")));
+    }
+    if schedule.days_of_week.values().next().is_some() {
+        by_weekday.push((Some(weekday_list(&schedule.days_of_week)), "-*".to_string()));
+    }
+    for rule in &schedule.day_rules {
+        match *rule {
+            DayRule::LastDay(offset) => by_date.push((None, format!("~{:02}", offset + 1))),
+            DayRule::LastOf(day) => {
+                by_weekday.push((Some(WEEKDAYS[day as usize].to_string()), "~07/1".to_string()))
+            }
+            DayRule::Nth(day, n) => by_weekday.push((
+                Some(WEEKDAYS[day as usize].to_string()),
+                format!("-{:02}..{:02}", 7 * n - 6, (7 * n).min(31)),
+            )),
+            DayRule::LastWeekday | DayRule::NearestWeekday(_) => warnings.push(
+                "`W` (nearest weekday) has no OnCalendar equivalent and was left out".to_string(),
+            ),
+        }
+    }
+
+    let days = match (schedule.dom_restricted, schedule.dow_restricted) {
+        (_, false) => by_date,
+        (false, true) => by_weekday,
+        (true, true) if schedule.days_ored => {
+            by_date.extend(by_weekday);
+            by_date
+        }
+        (true, true) => {
+            // ANDed: a plain weekday list combines with each date, but `L`
+            // and `#` weekdays already fix their own dates
+            if by_weekday.iter().any(|(_, date)| date != "-*") {
+                warnings.push(
+                    "`L`/`#` weekdays combined with a day of month can't be expressed, \
+                     only the plain weekdays were kept"
+                        .to_string(),
+                );
+            }
+            let weekdays = (schedule.days_of_week.values().next().is_some())
+                .then(|| weekday_list(&schedule.days_of_week));
+            by_date
+                .into_iter()
+                .map(|(_, date)| (weekdays.clone(), date))
+                .collect()
+        }
+    };
+
+    if days.is_empty() {
+        warnings.push("no OnCalendar= could be generated, the timer never fires".to_string());
+    }
+    days.into_iter()
+        .map(|(weekday, date)| match weekday {
+            Some(weekday) => format!("{weekday} {year}-{month}{date} {time}"),
+            None => format!("{year}-{month}{date} {time}"),
+        })
+        .collect()
+}
+
+/// `*` for a full field, otherwise values with runs of three or more
+/// collapsed into `a..b`.
+fn calendar_list(set: &FieldSet, min: u32, max: u32) -> String {
+    let values: Vec<u32> = set.values().filter(|v| (min..=max).contains(v)).collect();
+    if values.len() as u32 == max - min + 1 {
+        return "*".to_string();
+    }
+    runs(&values)
+        .iter()
+        .map(|&(low, high)| match high - low {
+            0 => format!("{low:02}"),
+            1 => format!("{low:02},{high:02}"),
+            _ => format!("{low:02}..{high:02}"),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn weekday_list(set: &FieldSet) -> String {
+    let values: Vec<u32> = set.values().filter(|v| *v < 7).collect();
+    runs(&values)
+        .iter()
+        .map(|&(low, high)| match high - low {
+            0 => WEEKDAYS[low as usize].to_string(),
+            1 => format!("{},{}", WEEKDAYS[low as usize], WEEKDAYS[high as usize]),
+            _ => format!("{}..{}", WEEKDAYS[low as usize], WEEKDAYS[high as usize]),
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+fn runs(values: &[u32]) -> Vec<(u32, u32)> {
+    let mut out: Vec<(u32, u32)> = Vec::new();
+    for &value in values {
+        match out.last_mut() {
+            Some((_, high)) if *high + 1 == value => *high = value,
+            _ => out.push((value, value)),
+        }
+    }
+    out
+}
+
+//------------------------------------------------------------------------------
+// Quoting
+//------------------------------------------------------------------------------
+
+/// Escape a value for a double quoted unit file string.
+fn escape(value: &str) -> String {
+    escape_specifiers(&value.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// `%` introduces specifiers such as `%h` in unit files.
+fn escape_specifiers(value: &str) -> String {
+    value.replace('%', "%%")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crontab;
+    use crate::lexer;
+    use crate::schedule::Dialect;
+
+    fn units(source: &str, dialect: Dialect) -> Vec<Units> {
+        let (tokens, _) = lexer::lex(source, dialect);
+        let crontab = crontab::parse(source, &tokens, dialect);
+        assert!(crontab.errors.is_empty(), "{source:?}: {:?}", crontab.errors);
+        crontab.entries.iter().map(|entry| translate(&crontab, entry)).collect()
+    }
+
+    /// The values of every `key=` line in a unit.
+    fn settings<'a>(unit: &'a str, key: &str) -> Vec<&'a str> {
+        unit.lines().filter_map(|line| line.strip_prefix(key)?.strip_prefix('=')).collect()
+    }
+
+    #[test]
+    fn calendars() {
+        let cases: &[(&str, Dialect, &[&str])] = &[
+            ("0 3 * * * /x\n", Dialect::Vixie, &["*-*-* 03:00:00"]),
+            // steps and ranges expand, runs of three or more collapse
+            ("*/15 9-17 * * 1-5 /x\n", Dialect::Vixie, &["Mon..Fri *-*-* 09..17:00,15,30,45:00"]),
+            ("*/20 */6 * 1,2 * /x\n", Dialect::Vixie, &["*-01,02-* 00,06,12,18:00,20,40:00"]),
+            ("0 0 * * 0,6 /x\n", Dialect::Vixie, &["Sun,Sat *-*-* 00:00:00"]),
+            // Vixie ORs restricted day fields, so one expression each
+            ("0 0 1,15 * 0 /x\n", Dialect::Vixie, &["*-*-01,15 00:00:00", "Sun *-*-* 00:00:00"]),
+            // Jenkins ANDs them, as systemd does
+            ("0 0 1,15 * 0 /x\n", Dialect::Jenkins, &["Sun *-*-01,15 00:00:00"]),
+            // seconds and years
+            ("30 0 12 * * ? 2026-2028 /x\n", Dialect::Quartz, &["2026..2028-*-* 12:00:30"]),
+            // L counts from the end of the month
+            ("0 0 0 L * ? /x\n", Dialect::Quartz, &["*-*~01 00:00:00"]),
+            ("0 0 0 L-2 * ? /x\n", Dialect::Quartz, &["*-*~03 00:00:00"]),
+            // the last Thursday is a Thursday in the last seven days
+            ("0 0 0 ? * 5L /x\n", Dialect::Quartz, &["Thu *-*~07/1 00:00:00"]),
+            // the third Friday is a Friday from the 15th to the 21st
+            ("0 0 0 ? * 6#3 /x\n", Dialect::Quartz, &["Fri *-*-15..21 00:00:00"]),
+            ("0 0 0 ? * FRI#5 /x\n", Dialect::Quartz, &["Fri *-*-29..31 00:00:00"]),
+            // W has no equivalent
+            ("0 0 0 15W * ? /x\n", Dialect::Quartz, &[]),
+        ];
+        for &(source, dialect, expected) in cases {
+            let units = units(source, dialect);
+            assert_eq!(settings(&units[0].timer, "OnCalendar"), expected, "{source:?}");
+        }
+    }
+
+    #[test]
+    fn timezone_and_reboot() {
+        let units = units("CRON_TZ=Europe/London\n0 3 * * * /x\n@reboot /y\n", Dialect::Vixie);
+        assert_eq!(settings(&units[0].timer, "OnCalendar"), ["*-*-* 03:00:00 Europe/London"]);
+        assert!(settings(&units[1].timer, "OnCalendar").is_empty());
+        assert_eq!(settings(&units[1].timer, "OnBootSec"), ["1min"]);
+    }
+
+    #[test]
+    fn redirects() {
+        let cases: &[(&str, &str, &[&str], &[&str])] = &[
+            ("/x", "/x", &[], &[]),
+            ("/x > /dev/null", "/x", &["null"], &[]),
+            ("/x >/var/log/x.log 2>&1", "/x", &["truncate:/var/log/x.log"], &["inherit"]),
+            // 2>&1 before stdout moves copies the journal, not the file
+            ("/x 2>&1 >> ~/x.log", "/x", &["append:%h/x.log"], &["journal"]),
+            ("/x 2>> /var/log/x.err", "/x", &[], &["append:/var/log/x.err"]),
+            ("/x 1> /tmp/100%.log", "/x", &["truncate:/tmp/100%%.log"], &[]),
+            // redirects before the end stay in the shell command
+            ("/x > /tmp/a | wc -l", "/x > /tmp/a | wc -l", &[], &[]),
+            // a relative target can't be expressed, so nothing is split off
+            ("/x > out.txt 2> /dev/null", "/x > out.txt 2> /dev/null", &[], &[]),
+        ];
+        for &(command, exec, stdout, stderr) in cases {
+            let units = units(&format!("0 3 * * * {command}\n"), Dialect::Vixie);
+            let service = &units[0].service;
+            let exec_start = format!("/bin/sh -c \"{exec}\"");
+            assert_eq!(settings(service, "ExecStart"), [exec_start], "{command:?}");
+            assert_eq!(settings(service, "StandardOutput"), stdout, "{command:?}");
+            assert_eq!(settings(service, "StandardError"), stderr, "{command:?}");
+        }
+    }
+
+    #[test]
+    fn escaping() {
+        let source = "SHELL=/bin/bash\n\
+                      GREETING=say \"hi\" 100%\n\
+                      0 3 * * * echo \"$HOME\" $(date +%F) > /dev/null\n";
+        let units = units(source, Dialect::Vixie);
+        let service = &units[0].service;
+        assert_eq!(
+            settings(service, "Environment"),
+            [r#""SHELL=/bin/bash""#, r#""GREETING=say \"hi\" 100%%""#]
+        );
+        assert_eq!(
+            settings(service, "ExecStart"),
+            [r#"/bin/bash -c "echo \"$$HOME\" $$(date +%%F)""#]
+        );
+        assert_eq!(settings(service, "Description"), [r#"cron line 3: echo "$HOME" $(date +%%F)"#]);
+        assert!(units[0].warnings.iter().any(|warning| warning.starts_with("`%`")));
+    }
+}
@@ -0,0 +1,342 @@
+use crate::crontab::Entry;
+use chrono::{Duration, NaiveDateTime, Timelike};
+use std::collections::BTreeMap;
+
+// Hot spots and overlaps beyond this many are summarised as "... and N more"
+const MAX_LISTED: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimelineFormat {
+    Ascii,
+    Csv,
+}
+
+pub struct TimelineOptions {
+    pub start: NaiveDateTime,
+    pub days: i64,
+    /// Estimated run time in minutes for jobs without a measured duration
+    pub default_duration: i64,
+    /// Measured run times: (minutes, command or command prefix)
+    pub durations: Vec<(i64, String)>,
+    /// Minimum number of jobs starting in one minute to report it
+    pub hotspot_threshold: usize,
+    pub format: TimelineFormat,
+}
+
+struct Firing<'a> {
+    entry: &'a Entry,
+    start: NaiveDateTime,
+    end: NaiveDateTime,
+}
+
+/// Parse a durations file: one `MINUTES COMMAND` pair per line, `#` comments.
+pub fn parse_durations(text: &str) -> Result<Vec<(i64, String)>, String> {
+    let mut durations = Vec::new();
+    for (idx, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (minutes, command) = line
+            .split_once(char::is_whitespace)
+            .ok_or_else(|| format!("line {}: expected `MINUTES COMMAND`", idx + 1))?;
+        let minutes: i64 = minutes
+            .parse()
+            .ok()
+            .filter(|&minutes| minutes > 0)
+            .ok_or_else(|| format!("line {}: invalid minutes `{minutes}`", idx + 1))?;
+        durations.push((minutes, command.trim().to_string()));
+    }
+    Ok(durations)
+}
+
+fn duration_of(entry: &Entry, options: &TimelineOptions) -> i64 {
+    options
+        .durations
+        .iter()
+        .find(|(_, command)| entry.command.starts_with(command.as_str()))
+        .map(|(minutes, _)| *minutes)
+        .unwrap_or(options.default_duration)
+        .max(1)
+}
+
+//------------------------------------------------------------------------------
+// Report
+//------------------------------------------------------------------------------
+
+/// Simulate every entry over the window and print hot spots, overlaps and
+/// a timeline. In CSV mode only the firings go to stdout so the output can
+/// be graphed directly; the analysis goes to stderr.
+pub fn print_report(entries: &[Entry], options: &TimelineOptions) {
+    let firings = simulate(entries, options);
+
+    let mut analysis = Vec::new();
+    analysis.extend(hotspot_lines(&firings, options.hotspot_threshold));
+    analysis.push(String::new());
+    analysis.extend(overlap_lines(&firings));
+
+    match options.format {
+        TimelineFormat::Csv => {
+            println!("start,end,line,command");
+            for firing in &firings {
+                println!(
+                    "{},{},{},\"{}\"",
+                    firing.start.format("%Y-%m-%d %H:%M"),
+                    firing.end.format("%Y-%m-%d %H:%M"),
+                    firing.entry.line,
+                    firing.entry.command.replace('"', "\"\"")
+                );
+            }
+            for line in analysis {
+                eprintln!("{line}");
+            }
+        }
+        TimelineFormat::Ascii => {
+            for line in ascii_lines(entries, &firings, options) {
+                println!("{line}");
+            }
+            println!();
+            for line in analysis {
+                println!("{line}");
+            }
+        }
+    }
+}
+
+/// Every firing in the window, in start order.
+fn simulate<'a>(entries: &'a [Entry], options: &TimelineOptions) -> Vec<Firing<'a>> {
+    let end = options.start + Duration::days(options.days);
+    let mut firings: Vec<Firing> = Vec::new();
+    for entry in entries {
+        let minutes = Duration::minutes(duration_of(entry, options));
+        for start in entry.schedule.firings(options.start, end) {
+            firings.push(Firing {
+                entry,
+                start,
+                end: start + minutes,
+            });
+        }
+    }
+    firings.sort_by_key(|f| (f.start, f.entry.line));
+    firings
+}
+
+fn hotspot_lines(firings: &[Firing], threshold: usize) -> Vec<String> {
+    let mut per_minute: BTreeMap<NaiveDateTime, Vec<usize>> = BTreeMap::new();
+    for firing in firings {
+        per_minute
+            .entry(minute_of(firing.start))
+            .or_default()
+            .push(firing.entry.line);
+    }
+
+    let mut hotspots: Vec<(NaiveDateTime, Vec<usize>)> = per_minute
+        .into_iter()
+        .filter(|(_, lines)| lines.len() >= threshold)
+        .collect();
+    // busiest first, then chronological (sort is stable)
+    hotspots.sort_by_key(|(_, lines)| std::cmp::Reverse(lines.len()));
+
+    let mut out = vec![format!(
+        "Hot spots ({threshold} or more jobs starting in the same minute): {}",
+        hotspots.len()
+    )];
+    for (time, lines) in hotspots.iter().take(MAX_LISTED) {
+        let lines: Vec<String> = lines.iter().map(|l| l.to_string()).collect();
+        out.push(format!(
+            "  {}  {} jobs  (lines {})",
+            time.format("%a %Y-%m-%d %H:%M"),
+            lines.len(),
+            lines.join(", ")
+        ));
+    }
+    if hotspots.len() > MAX_LISTED {
+        out.push(format!("  ... and {} more", hotspots.len() - MAX_LISTED));
+    }
+    out
+}
+
+fn overlap_lines(firings: &[Firing]) -> Vec<String> {
+    // (earlier line, later line) -> (first overlap, number of overlaps)
+    let mut overlaps: BTreeMap<(usize, usize), (NaiveDateTime, usize)> = BTreeMap::new();
+
+    // firings are sorted by start, so only the ones still running matter;
+    // jobs starting in the same minute are hot spots, not overlaps
+    let mut running: Vec<&Firing> = Vec::new();
+    for firing in firings {
+        running.retain(|r| r.end > firing.start);
+        for other in running
+            .iter()
+            .filter(|r| minute_of(r.start) < minute_of(firing.start))
+        {
+            let key = (
+                other.entry.line.min(firing.entry.line),
+                other.entry.line.max(firing.entry.line),
+            );
+            let slot = overlaps.entry(key).or_insert((firing.start, 0));
+            slot.1 += 1;
+        }
+        running.push(firing);
+    }
+
+    let mut out = vec![format!("Overlapping jobs: {}", overlaps.len())];
+    for ((a, b), (first, count)) in overlaps.iter().take(MAX_LISTED) {
+        let who = if a == b {
+            format!("line {a} overlaps its own previous run")
+        } else {
+            format!("line {a} overlaps line {b}")
+        };
+        out.push(format!(
+            "  {who}  {count} time(s), first at {}",
+            first.format("%a %Y-%m-%d %H:%M")
+        ));
+    }
+    if overlaps.len() > MAX_LISTED {
+        out.push(format!("  ... and {} more", overlaps.len() - MAX_LISTED));
+    }
+    out
+}
+
+/// Seconds-field dialects can fire mid-minute; the report works in minutes.
+fn minute_of(time: NaiveDateTime) -> NaiveDateTime {
+    time.with_second(0).unwrap_or(time)
+}
+
+//------------------------------------------------------------------------------
+// ASCII timeline
+//------------------------------------------------------------------------------
+
+fn ascii_lines(entries: &[Entry], firings: &[Firing], options: &TimelineOptions) -> Vec<String> {
+    // a day is drawn at 15 minutes per column, a week at one hour per column
+    let bucket = if options.days <= 1 { 15 } else { 60 };
+    let columns = (options.days * 24 * 60 / bucket) as usize;
+    let label_every = if options.days <= 1 { 12 } else { 24 };
+
+    let mut ruler = String::new();
+    while ruler.len() < columns {
+        let at = options.start + Duration::minutes(bucket * ruler.len() as i64);
+        let label = if options.days <= 1 {
+            at.format("%H").to_string()
+        } else {
+            at.format("%a").to_string()
+        };
+        ruler.push_str(&format!("{label:<label_every$}"));
+    }
+    ruler.truncate(columns);
+
+    let mut out = vec![
+        format!(
+            "Timeline {} .. {} (1 column = {bucket} min)",
+            options.start.format("%Y-%m-%d %H:%M"),
+            (options.start + Duration::days(options.days)).format("%Y-%m-%d %H:%M"),
+        ),
+        format!("{:>5} |{ruler}| entry", "line"),
+    ];
+
+    for entry in entries {
+        let mut counts = vec![0usize; columns];
+        for firing in firings.iter().filter(|f| f.entry.line == entry.line) {
+            let column = ((firing.start - options.start).num_minutes() / bucket) as usize;
+            counts[column.min(columns - 1)] += 1;
+        }
+        let row: String = counts
+            .iter()
+            .map(|&count| match count {
+                0 => '.',
+                1..=9 => char::from_digit(count as u32, 10).unwrap(),
+                _ => '+',
+            })
+            .collect();
+        out.push(format!(
+            "{:>5} |{row}| {} {}",
+            entry.line, entry.schedule_text, entry.command
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crontab;
+    use crate::lexer;
+    use crate::schedule::Dialect;
+
+    fn entries(source: &str) -> Vec<Entry> {
+        let (tokens, _) = lexer::lex(source, Dialect::Vixie);
+        crontab::parse(source, &tokens, Dialect::Vixie).entries
+    }
+
+    fn options(durations: &str) -> TimelineOptions {
+        TimelineOptions {
+            start: NaiveDateTime::parse_from_str("2026-10-19 00:00", "%Y-%m-%d %H:%M")
+                .expect("a valid time"),
+            days: 1,
+            default_duration: 1,
+            durations: parse_durations(durations).expect("valid durations"),
+            hotspot_threshold: 2,
+            format: TimelineFormat::Ascii,
+        }
+    }
+
+    #[test]
+    fn jobs_in_the_same_minute_are_a_hot_spot() {
+        let entries =
+            entries("0 9 * * * /usr/bin/a\n0 9 * * * /usr/bin/b\n0 10 * * * /usr/bin/c\n");
+        let options = options("");
+        let firings = simulate(&entries, &options);
+        assert_eq!(
+            hotspot_lines(&firings, options.hotspot_threshold),
+            [
+                "Hot spots (2 or more jobs starting in the same minute): 1",
+                "  Mon 2026-10-19 09:00  2 jobs  (lines 1, 2)",
+            ]
+        );
+        // starting together is a hot spot, not an overlap
+        assert_eq!(overlap_lines(&firings), ["Overlapping jobs: 0"]);
+    }
+
+    #[test]
+    fn long_runs_overlap_later_starts() {
+        let entries = entries(
+            "0 9 * * * /usr/bin/slow --all\n15 9 * * * /usr/bin/quick\n5 */6 * * * /usr/bin/poll\n",
+        );
+        // poll runs for 6h40m, so into its own next run
+        let options = options("# measured\n30 /usr/bin/slow\n400 /usr/bin/poll\n");
+        let firings = simulate(&entries, &options);
+        assert_eq!(
+            overlap_lines(&firings),
+            [
+                "Overlapping jobs: 4",
+                "  line 1 overlaps line 2  1 time(s), first at Mon 2026-10-19 09:15",
+                "  line 1 overlaps line 3  1 time(s), first at Mon 2026-10-19 09:00",
+                "  line 2 overlaps line 3  1 time(s), first at Mon 2026-10-19 09:15",
+                "  line 3 overlaps its own previous run  3 time(s), first at Mon 2026-10-19 06:05",
+            ]
+        );
+        assert_eq!(
+            hotspot_lines(&firings, 2),
+            ["Hot spots (2 or more jobs starting in the same minute): 0"]
+        );
+    }
+
+    #[test]
+    fn durations_file() {
+        assert_eq!(
+            parse_durations("# minutes command\n\n  45 /usr/bin/backup --full  \n5\t/usr/bin/x\n"),
+            Ok(vec![
+                (45, "/usr/bin/backup --full".to_string()),
+                (5, "/usr/bin/x".to_string()),
+            ])
+        );
+        for (text, error) in [
+            ("45\n", "line 1: expected `MINUTES COMMAND`"),
+            ("# ok\nfive /usr/bin/x\n", "line 2: invalid minutes `five`"),
+            ("1.5 /usr/bin/x\n", "line 1: invalid minutes `1.5`"),
+            ("0 /usr/bin/x\n", "line 1: invalid minutes `0`"),
+            ("-5 /usr/bin/x\n", "line 1: invalid minutes `-5`"),
+        ] {
+            assert_eq!(parse_durations(text), Err(error.to_string()), "{text:?}");
+        }
+    }
+}
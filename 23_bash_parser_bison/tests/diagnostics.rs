@@ -0,0 +1,84 @@
+//! Renders a fixed set of diagnostics as JSON and SARIF and compares them
+//! with the golden files in `tests/diagnostics/`, which CI consumers rely
+//! on. Set `BLESS=1` to rewrite them after an intended change, then review
+//! the diff.
+
+use croncheck::crontab::LineIndex;
+use croncheck::diagnostics::{render, Diagnostic, Format, Severity};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+// a multi-byte character before the span, as columns count code points
+const SOURCE: &str = "MAILTO=root\n0 5 * * * café /no/such\nbad line\n";
+
+fn diagnostics() -> Vec<Diagnostic> {
+    let index = LineIndex::new(SOURCE);
+    let span = |text: &str| {
+        let start = SOURCE.find(text).expect("text in the source");
+        start..start + text.len()
+    };
+    vec![
+        Diagnostic::new(
+            "missing-path",
+            Severity::Warning,
+            &index,
+            &span("/no/such"),
+            "path does not exist: /no/such".to_string(),
+        ),
+        Diagnostic::new(
+            "invalid-entry",
+            Severity::Error,
+            &index,
+            &span("bad line"),
+            "expected five schedule fields".to_string(),
+        ),
+        Diagnostic::new("path-reference", Severity::Note, &index, &span("/no/such"), "/no/such".to_string()),
+    ]
+}
+
+fn check_golden(name: &str, actual: &str) {
+    // the version is the crate's, so it isn't pinned
+    let actual = actual.replace(env!("CARGO_PKG_VERSION"), "VERSION") + "\n";
+    let golden = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/diagnostics").join(name);
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(&golden, &actual).expect("golden file is writable");
+        return;
+    }
+    let expected = fs::read_to_string(&golden)
+        .unwrap_or_else(|e| panic!("{}: {e}; run with BLESS=1 to create it", golden.display()));
+    assert_eq!(actual, expected, "{name} differs from its golden file");
+}
+
+#[test]
+fn json_matches_golden() {
+    check_golden("jobs.json", &render(&diagnostics(), "jobs.crontab", Format::Json));
+}
+
+#[test]
+fn sarif_matches_golden() {
+    let sarif = render(&diagnostics(), "jobs.crontab", Format::Sarif);
+    check_golden("jobs.sarif", &sarif);
+
+    // the parts consumers key on, spelled out
+    let log: Value = serde_json::from_str(&sarif).expect("SARIF is JSON");
+    assert_eq!(log["version"], "2.1.0");
+    assert_eq!(log["$schema"], "https://json.schemastore.org/sarif-2.1.0.json");
+    let run = &log["runs"][0];
+    assert_eq!(run["tool"]["driver"]["name"], "croncheck");
+    let rules = run["tool"]["driver"]["rules"].as_array().expect("a rule table");
+    for result in run["results"].as_array().expect("results") {
+        let index = result["ruleIndex"].as_u64().expect("a rule index") as usize;
+        assert_eq!(rules[index]["id"], result["ruleId"]);
+    }
+    let levels: Vec<&str> = run["results"]
+        .as_array()
+        .expect("results")
+        .iter()
+        .map(|result| result["level"].as_str().expect("a level"))
+        .collect();
+    assert_eq!(levels, ["warning", "error", "note"]);
+    let region = &run["results"][0]["locations"][0]["physicalLocation"]["region"];
+    assert_eq!((region["startLine"].as_u64(), region["startColumn"].as_u64()), (Some(2), Some(16)));
+    assert_eq!((region["endLine"].as_u64(), region["endColumn"].as_u64()), (Some(2), Some(24)));
+}
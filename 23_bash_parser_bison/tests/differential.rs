@@ -0,0 +1,34 @@
+//! Runs both lexers over croncheck's crontab corpus and compares their
+//! disagreements with the accepted ones in `differential.expected`, so
+//! only a *new* divergence fails. Set `BLESS=1` to write the current
+//! report after reviewing it.
+
+use croncheck::antlr::{corpus_files, report};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[test]
+fn corpus_disagreements_match_expected() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let files = corpus_files(&[root.join("corpus")]).expect("corpus is readable");
+    assert!(!files.is_empty(), "corpus is empty");
+
+    let (actual, _) = report(&files).expect("corpus files are readable");
+    let expected_path: PathBuf = root.join("tests/differential.expected");
+    if std::env::var_os("BLESS").is_some() {
+        fs::write(&expected_path, &actual).expect("can write the expected report");
+        eprintln!("wrote {}", expected_path.display());
+        return;
+    }
+
+    let expected = fs::read_to_string(&expected_path).unwrap_or_else(|e| {
+        panic!(
+            "{}: {e}; run with BLESS=1 to write it",
+            expected_path.display()
+        )
+    });
+    assert_eq!(
+        expected, actual,
+        "lexers disagree differently than expected; rerun with BLESS=1 to accept"
+    );
+}
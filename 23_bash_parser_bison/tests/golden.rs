@@ -0,0 +1,73 @@
+//! Lexes every crontab in `corpus/` and compares the token stream with the
+//! `.tokens` file next to it. Set `BLESS=1` to rewrite the golden files
+//! after an intended change, then review the diff.
+
+use croncheck::crontab::LineIndex;
+use croncheck::lexer::{lex, token_label};
+use croncheck::schedule::Dialect;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// `line:column LABEL text` per token, lex errors as `line:column error: message`.
+fn render(source: &str) -> String {
+    let index = LineIndex::new(source);
+    let position = |offset: usize| format!("{}:{}", index.line(offset), index.column(offset));
+    let (tokens, errors) = lex(source, Dialect::Vixie);
+
+    let mut lines: Vec<(usize, String)> = tokens
+        .iter()
+        .map(|(token, span)| {
+            let text = &source[span.clone()];
+            let line = format!("{:<6} {:<8} {text}", position(span.start), token_label(token));
+            (span.start, line)
+        })
+        .collect();
+    lines.extend(errors.iter().map(|error| {
+        let line = format!("{:<6} error: {}", position(error.span.start), error.message);
+        (error.span.start, line)
+    }));
+    lines.sort_by_key(|(start, _)| *start);
+
+    let mut out: String = lines.into_iter().map(|(_, line)| line + "\n").collect();
+    if out.is_empty() {
+        out.push_str("(no tokens)\n");
+    }
+    out
+}
+
+fn corpus() -> Vec<PathBuf> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus");
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .expect("corpus directory exists")
+        .map(|entry| entry.expect("corpus entry is readable").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "crontab"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn corpus_matches_golden_tokens() {
+    let bless = std::env::var_os("BLESS").is_some();
+    let mut mismatches = Vec::new();
+
+    for crontab in corpus() {
+        let source = fs::read_to_string(&crontab).expect("crontab is readable");
+        let actual = render(&source);
+        let golden = crontab.with_extension("tokens");
+        if bless {
+            fs::write(&golden, &actual).expect("golden file is writable");
+            continue;
+        }
+        match fs::read_to_string(&golden) {
+            Ok(expected) if expected == actual => {}
+            Ok(expected) => mismatches.push(format!(
+                "{}:\n--- expected\n{expected}--- actual\n{actual}",
+                golden.display()
+            )),
+            Err(_) => mismatches.push(format!("{} is missing, run with BLESS=1", golden.display())),
+        }
+    }
+
+    assert!(mismatches.is_empty(), "token streams changed:\n{}", mismatches.join("\n"));
+}
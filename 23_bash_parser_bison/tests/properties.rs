@@ -0,0 +1,142 @@
+//! Property tests for the lexer: generated schedules and commands must lex
+//! without errors, tokens must cover the text exactly, and the token kinds
+//! that depend on the order of `cron_lexer`'s alternatives must not change.
+
+use croncheck::crontab;
+use croncheck::lexer::{lex, Token};
+use croncheck::schedule::{Dialect, Schedule};
+use proptest::prelude::*;
+
+//------------------------------------------------------------------------------
+// Generators
+//------------------------------------------------------------------------------
+
+/// One schedule field within `min..=max`: `*`, a value, a range, a step or
+/// a list of values.
+fn field(min: u32, max: u32) -> impl Strategy<Value = String> {
+    let value = min..=max;
+    prop_oneof![
+        Just("*".to_string()),
+        value.clone().prop_map(|v| v.to_string()),
+        (value.clone(), value.clone())
+            .prop_map(|(a, b)| format!("{}-{}", a.min(b), a.max(b))),
+        (1..=max).prop_map(|step| format!("*/{step}")),
+        prop::collection::vec(value, 2..4).prop_map(|values| {
+            values.iter().map(u32::to_string).collect::<Vec<_>>().join(",")
+        }),
+    ]
+}
+
+fn schedule() -> impl Strategy<Value = Vec<String>> {
+    (field(0, 59), field(0, 23), field(1, 31), field(1, 12), field(0, 7))
+        .prop_map(|(m, h, dom, mon, dow)| vec![m, h, dom, mon, dow])
+}
+
+fn program() -> impl Strategy<Value = String> {
+    "[a-z][a-z0-9_.]{0,8}"
+}
+
+fn path() -> impl Strategy<Value = String> {
+    prop::collection::vec("[a-z][a-z0-9_.]{0,5}", 1..4).prop_map(|segments| {
+        format!("/{}", segments.join("/"))
+    })
+}
+
+fn long_option() -> impl Strategy<Value = String> {
+    "--[a-z][a-z0-9-]{0,6}"
+}
+
+fn url() -> impl Strategy<Value = String> {
+    "https://[a-z]{1,8}\\.com(/[a-z]{1,5}){0,2}"
+}
+
+fn command() -> impl Strategy<Value = Vec<String>> {
+    let word = prop_oneof![program(), path(), long_option(), url()];
+    (path(), prop::collection::vec(word, 0..5)).prop_map(|(program, args)| {
+        std::iter::once(program).chain(args).collect()
+    })
+}
+
+//------------------------------------------------------------------------------
+// Properties
+//------------------------------------------------------------------------------
+
+proptest! {
+    #[test]
+    fn generated_entries_lex_and_cover_the_text(schedule in schedule(), command in command()) {
+        let line = format!("{} {}\n", schedule.join(" "), command.join(" "));
+        let (tokens, errors) = lex(&line, Dialect::Vixie);
+        prop_assert!(errors.is_empty(), "{errors:?} for {line:?}");
+
+        let covered: String = tokens.iter().map(|(_, span)| &line[span.clone()]).collect();
+        let expected: String = line.split_whitespace().collect();
+        prop_assert_eq!(covered, expected);
+        for pair in tokens.windows(2) {
+            prop_assert!(pair[0].1.end <= pair[1].1.start, "overlapping spans in {line:?}");
+        }
+    }
+
+    #[test]
+    fn generated_entries_round_trip_through_the_parser(
+        schedule in schedule(),
+        command in command(),
+    ) {
+        let line = format!("{} {}\n", schedule.join(" "), command.join(" "));
+        let (tokens, _) = lex(&line, Dialect::Vixie);
+        let parsed = crontab::parse(&line, &tokens, Dialect::Vixie);
+        prop_assert!(parsed.errors.is_empty(), "{:?} for {line:?}", parsed.errors);
+        prop_assert_eq!(parsed.entries.len(), 1);
+
+        let entry = &parsed.entries[0];
+        let fields: Vec<&str> = schedule.iter().map(String::as_str).collect();
+        let command = command.join(" ");
+        let expected = Schedule::parse(&fields, Dialect::Vixie, &command).expect("generated fields are valid");
+        prop_assert_eq!(&entry.schedule, &expected);
+        prop_assert_eq!(&entry.schedule_text, &schedule.join(" "));
+        prop_assert_eq!(&entry.command, &command);
+    }
+
+    #[test]
+    fn command_words_keep_their_token_kind(
+        path in path(),
+        option in long_option(),
+        url in url(),
+        fd in prop::option::of(1..=2u8),
+    ) {
+        let redirect = fd.map_or(">".to_string(), |fd| format!("{fd}>"));
+        let line = format!("* * * * * {path} {option} {url} {redirect}{path}\n");
+        let (tokens, errors) = lex(&line, Dialect::Vixie);
+        prop_assert!(errors.is_empty());
+
+        let command: Vec<&Token> = tokens[5..].iter().map(|(token, _)| token).collect();
+        prop_assert_eq!(command[0], &Token::Path(path.clone()));
+        prop_assert_eq!(command[1], &Token::CliOption(option));
+        prop_assert_eq!(command[2], &Token::HttpUrl(url));
+        prop_assert_eq!(command[3], &Token::Redirect(redirect));
+        prop_assert_eq!(command[4], &Token::Path(path));
+    }
+
+    #[test]
+    fn steps_are_equivalent_to_their_expansion(step in 1..=30u32, hour_step in 1..=12u32) {
+        let expand = |max: u32, step: u32| {
+            (0..=max).step_by(step as usize).map(|v| v.to_string()).collect::<Vec<_>>().join(",")
+        };
+        let stepped = [format!("*/{step}"), format!("*/{hour_step}"), "*".into(), "*".into(), "*".into()];
+        let listed = [expand(59, step), expand(23, hour_step), "*".into(), "*".into(), "*".into()];
+        let parse = |fields: &[String; 5]| {
+            let fields: Vec<&str> = fields.iter().map(String::as_str).collect();
+            Schedule::parse(&fields, Dialect::Vixie, "job").expect("valid fields")
+        };
+        prop_assert!(parse(&stepped).equivalent(&parse(&listed)));
+    }
+
+    #[test]
+    fn arbitrary_text_never_panics(source in "\\PC*( |\t|\n|#|\"|'|>|&|=|/)*\\PC*") {
+        let (tokens, errors) = lex(&source, Dialect::Vixie);
+        for span in tokens.iter().map(|(_, span)| span).chain(errors.iter().map(|e| &e.span)) {
+            prop_assert!(span.start <= span.end && span.end <= source.len());
+            prop_assert!(source.is_char_boundary(span.start) && source.is_char_boundary(span.end));
+        }
+        crontab::parse(&source, &tokens, Dialect::Vixie);
+    }
+}
@@ -0,0 +1,129 @@
+use crate::document;
+use crate::order;
+use chrono::{DateTime, Local, NaiveDate};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+// Beside the notes file
+pub const ARCHIVE_DIR: &str = "archive";
+const LOG_FILE: &str = "archive.log";
+
+/// A snippet taken out of the notes, and the date that made it old.
+pub struct Archived {
+    pub snippet: String,
+    pub date: NaiveDate,
+}
+
+impl Archived {
+    pub fn heading(&self) -> String {
+        document::snippet_heading(&self.snippet)
+    }
+
+    /// `archive/YYYY.mwk`, relative to the notes file.
+    pub fn file(&self) -> PathBuf {
+        Path::new(ARCHIVE_DIR).join(format!("{}.mwk", self.date.format("%Y")))
+    }
+}
+
+/// The date each line of `path` was last committed, from `git blame`, or
+/// `None` if it isn't tracked by git. Uncommitted lines count as today.
+pub fn blame_dates(path: &Path) -> Option<Vec<NaiveDate>> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir)
+        .args(["blame", "--line-porcelain", "--"])
+        .arg(path.file_name()?)
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    // every line's header repeats its commit's details
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.strip_prefix("committer-time "))
+        .map(|seconds| {
+            let time = DateTime::from_timestamp(seconds.parse().ok()?, 0)?;
+            Some(time.with_timezone(&Local).date_naive())
+        })
+        .collect()
+}
+
+/// Take out the unpinned snippets dated before `cutoff`: by the
+/// `YYYY-MM-DD` date in their heading, or failing that the newest of their
+/// lines in `blame`, which starts at the snippets' `first_line` (0-based).
+pub fn take_old(
+    snippets: &mut Vec<String>,
+    first_line: usize,
+    blame: Option<&[NaiveDate]>,
+    cutoff: NaiveDate,
+) -> Vec<Archived> {
+    let mut archived = Vec::new();
+    let mut kept = Vec::new();
+    let mut line = first_line;
+    for snippet in snippets.drain(..) {
+        let lines = snippet.lines().count();
+        let heading_date = order::heading_date(&document::snippet_heading(&snippet))
+            .and_then(|(year, month, day)| NaiveDate::from_ymd_opt(year as i32, month, day));
+        let blame_date = blame
+            .and_then(|blame| blame.get(line..line + lines))
+            .and_then(|dates| dates.iter().max().copied());
+        line += lines;
+
+        let pinned = document::parse(&snippet)
+            .hashtags()
+            .iter()
+            .any(|tag| matches!(*tag, "pin" | "pin-top" | "pin-bottom"));
+        match heading_date.or(blame_date) {
+            Some(date) if date < cutoff && !pinned => archived.push(Archived { snippet, date }),
+            _ => kept.push(snippet),
+        }
+    }
+    *snippets = kept;
+    archived
+}
+
+/// Append each snippet to its year's file under `archive/` beside `notes`,
+/// and record it in `archive/archive.log` as a tab-separated line of the
+/// time, notes file, archive file and heading, so it can be found and moved
+/// back. Call this before rewriting `notes`.
+pub fn write(notes: &Path, archived: &[Archived]) -> io::Result<()> {
+    if archived.is_empty() {
+        return Ok(());
+    }
+    let dir = notes.parent().unwrap_or(Path::new(""));
+    fs::create_dir_all(dir.join(ARCHIVE_DIR))?;
+    let now = Local::now().format("%Y-%m-%d %H:%M:%S");
+    let name = notes.file_name().unwrap_or_default().to_string_lossy();
+
+    let mut log = String::new();
+    for entry in archived {
+        let mut snippet = entry.snippet.clone();
+        if !snippet.ends_with('\n') {
+            snippet.push('\n');
+        }
+        // one write per snippet, so runs over other notes files in the same
+        // directory don't interleave
+        append(&dir.join(entry.file()), &snippet)?;
+        log.push_str(&format!(
+            "{now}\t{name}\t{}\t{}\n",
+            entry.file().display(),
+            entry.heading()
+        ));
+    }
+    append(&dir.join(ARCHIVE_DIR).join(LOG_FILE), &log)
+}
+
+fn append(path: &Path, text: &str) -> io::Result<()> {
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?
+        .write_all(text.as_bytes())
+}
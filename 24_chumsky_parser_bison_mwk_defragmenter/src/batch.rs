@@ -0,0 +1,150 @@
+use crate::{archive, defragment_bytes, files, stream, Options};
+use rayon::prelude::*;
+use std::io;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// What happened to one file.
+enum Outcome {
+    Changed {
+        snippets: usize,
+        moved: usize,
+        archived: usize,
+        /// Set when the file was rewritten
+        backup: Option<PathBuf>,
+        /// Set for `--diff`
+        diff: Option<String>,
+    },
+    Unchanged {
+        snippets: usize,
+    },
+    Failed(io::Error),
+}
+
+/// `paths` with each directory replaced by the `.mwk` files under it, in
+/// name order, leaving out `archive` directories.
+pub fn mwk_files(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for path in paths {
+        if !path.is_dir() {
+            files.push(path.clone());
+            continue;
+        }
+        // skipping what earlier runs archived
+        let walk = WalkDir::new(path)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || entry.file_name() != archive::ARCHIVE_DIR);
+        for entry in walk {
+            let entry = entry.map_err(io::Error::from)?;
+            if entry.file_type().is_file() && entry.path().extension() == Some("mwk".as_ref()) {
+                files.push(entry.into_path());
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Defragment every file in parallel, then print any diffs and a report
+/// with a line per file. Unchanged files are never rewritten, and a file
+/// that fails doesn't stop the others. Returns whether every file could be
+/// read and written.
+pub fn run(files: &[PathBuf], options: &Options, in_place: bool, dry_run: bool) -> bool {
+    let outcomes: Vec<Outcome> = files
+        .par_iter()
+        .map(|file| process(file, options, in_place && !dry_run, dry_run))
+        .collect();
+
+    for outcome in &outcomes {
+        if let Outcome::Changed {
+            diff: Some(diff), ..
+        } = outcome
+        {
+            print!("{diff}");
+        }
+    }
+
+    println!("{:>8} {:>6} {:>8}  file", "snippets", "moved", "archived");
+    let (mut changed, mut unchanged, mut failed) = (0, 0, 0);
+    for (file, outcome) in files.iter().zip(&outcomes) {
+        let name = file.display();
+        match outcome {
+            Outcome::Changed {
+                snippets,
+                moved,
+                archived,
+                backup,
+                ..
+            } => {
+                changed += 1;
+                match backup {
+                    Some(backup) => println!(
+                        "{snippets:>8} {moved:>6} {archived:>8}  {name} (backup in {})",
+                        backup.display()
+                    ),
+                    None => println!("{snippets:>8} {moved:>6} {archived:>8}  {name}"),
+                }
+            }
+            Outcome::Unchanged { snippets } => {
+                unchanged += 1;
+                println!("{snippets:>8} {:>6} {:>8}  {name} (unchanged)", 0, 0);
+            }
+            Outcome::Failed(e) => {
+                failed += 1;
+                println!("{:>8} {:>6} {:>8}  {name}: {e}", "-", "-", "-");
+            }
+        }
+    }
+
+    let changed_label = if in_place && !dry_run {
+        "defragmented"
+    } else {
+        "would change"
+    };
+    println!(
+        "{} files: {changed} {changed_label}, {unchanged} unchanged, {failed} failed",
+        files.len()
+    );
+    failed == 0
+}
+
+fn process(file: &Path, options: &Options, in_place: bool, dry_run: bool) -> Outcome {
+    let input = match stream::open(file) {
+        Ok(input) => input,
+        Err(e) => return Outcome::Failed(e),
+    };
+    let defragmented = match defragment_bytes(&input, options, Some(file)) {
+        Ok(defragmented) => defragmented,
+        Err(e) => return Outcome::Failed(e),
+    };
+    if defragmented.unchanged(&input) {
+        return Outcome::Unchanged {
+            snippets: defragmented.snippets,
+        };
+    }
+
+    let backup = if in_place {
+        // the archive first, so the snippets are always somewhere
+        let replaced = archive::write(file, &defragmented.archived)
+            .and_then(|_| files::replace(file, |out| defragmented.write_to(&input, out)));
+        match replaced {
+            Ok(backup) => Some(backup),
+            Err(e) => return Outcome::Failed(e),
+        }
+    } else {
+        None
+    };
+    Outcome::Changed {
+        snippets: defragmented.snippets,
+        moved: defragmented.moved,
+        archived: defragmented.archived.len(),
+        backup,
+        diff: dry_run.then(|| {
+            files::diff(
+                file,
+                &String::from_utf8_lossy(&input),
+                &defragmented.text(&input),
+            )
+        }),
+    }
+}
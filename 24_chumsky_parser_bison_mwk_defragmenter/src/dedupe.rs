@@ -0,0 +1,245 @@
+use crate::document;
+
+/// Snippets at least this similar count as duplicates unless
+/// `--similarity` says otherwise.
+pub const DEFAULT_SIMILARITY: f64 = 0.9;
+
+/// What to do with a snippet that duplicates an earlier one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Remove it
+    Drop,
+    /// Remove it, appending its lines the earlier snippet lacks
+    Merge,
+    /// Keep it and list it on stderr
+    Report,
+}
+
+impl Mode {
+    pub fn from_name(name: &str) -> Option<Mode> {
+        match name {
+            "drop" => Some(Mode::Drop),
+            "merge" => Some(Mode::Merge),
+            "report" => Some(Mode::Report),
+            _ => None,
+        }
+    }
+}
+
+/// A snippet found to duplicate an earlier one.
+struct Duplicate {
+    /// Index of the duplicate and of the first snippet it repeats
+    index: usize,
+    original: usize,
+    similarity: f64,
+}
+
+/// Find snippets that repeat an earlier one, comparing them with runs of
+/// whitespace collapsed. Identical snippets have similarity 1; others are
+/// scored by shared character pairs (Sørensen–Dice) and count when they
+/// reach `threshold`. Every duplicate is listed on stderr; in drop and
+/// merge modes it's also removed.
+pub fn dedupe(snippets: &mut Vec<String>, mode: Mode, threshold: f64) {
+    let normalised: Vec<String> = snippets.iter().map(|s| normalise(s)).collect();
+    let pairs: Vec<usize> = normalised.iter().map(|s| bigram_count(s)).collect();
+
+    // compare each snippet with the first of every earlier set of copies
+    let mut originals: Vec<usize> = Vec::new();
+    let mut duplicates: Vec<Duplicate> = Vec::new();
+    for (index, text) in normalised.iter().enumerate() {
+        let best = originals
+            .iter()
+            .filter(|&&original| could_reach(pairs[original], pairs[index], threshold))
+            .map(|&original| (original, similarity(&normalised[original], text)))
+            .filter(|&(_, similarity)| similarity >= threshold)
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        match best {
+            Some((original, similarity)) => duplicates.push(Duplicate {
+                index,
+                original,
+                similarity,
+            }),
+            None => originals.push(index),
+        }
+    }
+
+    for duplicate in &duplicates {
+        eprintln!(
+            "{}: `{}` {} `{}` ({:.0}% similar)",
+            match mode {
+                Mode::Drop => "dropped",
+                Mode::Merge => "merged",
+                Mode::Report => "duplicate",
+            },
+            document::snippet_heading(&snippets[duplicate.index]),
+            if mode == Mode::Merge {
+                "into"
+            } else {
+                "repeats"
+            },
+            document::snippet_heading(&snippets[duplicate.original]),
+            duplicate.similarity * 100.0
+        );
+    }
+    if mode == Mode::Report {
+        return;
+    }
+
+    if mode == Mode::Merge {
+        for duplicate in &duplicates {
+            let merged = merge(&snippets[duplicate.original], &snippets[duplicate.index]);
+            snippets[duplicate.original] = merged;
+        }
+    }
+    let mut index = 0;
+    snippets.retain(|_| {
+        index += 1;
+        !duplicates
+            .iter()
+            .any(|duplicate| duplicate.index == index - 1)
+    });
+}
+
+/// `snippet` with runs of whitespace collapsed to one space.
+pub fn normalise(snippet: &str) -> String {
+    snippet.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// How alike two normalised snippets are, from 0 to 1.
+pub fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        1.0
+    } else {
+        strsim::sorensen_dice(a, b)
+    }
+}
+
+/// Dice scores pairs of adjacent characters, ignoring whitespace.
+fn bigram_count(text: &str) -> usize {
+    text.chars()
+        .filter(|c| !c.is_whitespace())
+        .count()
+        .saturating_sub(1)
+}
+
+/// Whether texts with these bigram counts could score `threshold`, so most
+/// pairs are never compared.
+fn could_reach(a: usize, b: usize, threshold: f64) -> bool {
+    a + b == 0 || 2.0 * a.min(b) as f64 / (a + b) as f64 >= threshold
+}
+
+/// `original` followed by the lines of `duplicate`, after its heading,
+/// that `original` doesn't already have.
+fn merge(original: &str, duplicate: &str) -> String {
+    let mut merged = original.to_string();
+    if !merged.is_empty() && !merged.ends_with('\n') {
+        merged.push('\n');
+    }
+    let existing: Vec<String> = original.lines().map(normalise).collect();
+    for line in duplicate.lines().skip(1) {
+        if !line.trim().is_empty() && !existing.contains(&normalise(line)) {
+            merged.push_str(line);
+            merged.push('\n');
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(snippets: &[&str]) -> Vec<String> {
+        snippets.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn whitespace_differences_are_identical() {
+        let mut snippets = strings(&["=== a\none  two\n", "===  a\n\none two", "=== b\n"]);
+        dedupe(&mut snippets, Mode::Drop, 1.0);
+        assert_eq!(snippets, strings(&["=== a\none  two\n", "=== b\n"]));
+    }
+
+    #[test]
+    fn threshold_is_inclusive() {
+        let (a, b) = (
+            "=== notes\nbuy milk and bread",
+            "=== notes\nbuy milk and bread!",
+        );
+        let score = similarity(&normalise(a), &normalise(b));
+        assert!(score > 0.9 && score < 1.0, "{score}");
+
+        let mut at = strings(&[a, b]);
+        dedupe(&mut at, Mode::Drop, score);
+        assert_eq!(at, strings(&[a]));
+
+        let mut above = strings(&[a, b]);
+        dedupe(&mut above, Mode::Drop, score + 1e-9);
+        assert_eq!(above, strings(&[a, b]));
+    }
+
+    #[test]
+    fn default_threshold_keeps_different_snippets() {
+        let mut snippets = strings(&["=== a\nsame text here\n", "=== b\nquite another thing\n"]);
+        dedupe(&mut snippets, Mode::Drop, DEFAULT_SIMILARITY);
+        assert_eq!(snippets.len(), 2);
+    }
+
+    #[test]
+    fn prefilter_never_skips_a_match() {
+        let texts = [
+            "",
+            "a",
+            "ab",
+            "abc",
+            "abcd",
+            "abcde x",
+            "abcdef xy",
+            "bcdefg",
+        ];
+        for threshold in [0.0, 0.5, 0.8, 0.9, 1.0] {
+            for a in texts {
+                for b in texts {
+                    if similarity(a, b) >= threshold {
+                        assert!(
+                            could_reach(bigram_count(a), bigram_count(b), threshold),
+                            "{a:?} {b:?} at {threshold}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn duplicates_repeat_the_first_copy() {
+        let mut snippets = strings(&[
+            "=== x\nabcdefghij",
+            "=== y\n",
+            "=== x\nabcdefghij",
+            "=== x\nabcdefghij",
+        ]);
+        dedupe(&mut snippets, Mode::Merge, 0.9);
+        assert_eq!(snippets, strings(&["=== x\nabcdefghij\n", "=== y\n"]));
+    }
+
+    #[test]
+    fn merge_appends_missing_lines() {
+        let mut snippets = strings(&[
+            "=== list\n- one\n- two\n- three\n- four\n",
+            "=== list\n- one\n-  two\n- three\n- four\n- five\n",
+        ]);
+        dedupe(&mut snippets, Mode::Merge, 0.8);
+        assert_eq!(
+            snippets,
+            strings(&["=== list\n- one\n- two\n- three\n- four\n- five\n"])
+        );
+    }
+
+    #[test]
+    fn report_keeps_everything() {
+        let mut snippets = strings(&["=== a\n", "=== a\n"]);
+        dedupe(&mut snippets, Mode::Report, 0.9);
+        assert_eq!(snippets.len(), 2);
+    }
+}
@@ -0,0 +1,519 @@
+use chumsky::prelude::*;
+
+/// Byte range in the mwk source.
+pub type Span = std::ops::Range<usize>;
+
+//------------------------------------------------------------------------------
+// Document tree
+//------------------------------------------------------------------------------
+
+/// Inline content of a text line or list item.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Inline {
+    Text(String),
+    /// `#tag`, without the `#`
+    Hashtag(String),
+    /// `https://...`, `[text](target)` or `[[target]]`
+    Link {
+        text: Option<String>,
+        target: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Block {
+    /// Consecutive text lines
+    Paragraph {
+        span: Span,
+        inlines: Vec<Inline>,
+    },
+    /// `- item`, `* item`, `+ item` or `1. item`
+    ListItem {
+        span: Span,
+        indent: usize,
+        marker: String,
+        inlines: Vec<Inline>,
+    },
+    /// A ```` ``` ```` fenced code block; an unclosed fence runs to the end
+    Code {
+        span: Span,
+        info: String,
+        code: String,
+    },
+    Blank {
+        span: Span,
+    },
+}
+
+/// A heading and everything up to the next heading of the same or a
+/// higher level (fewer `=`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Section {
+    /// Number of `=` in the heading
+    pub level: usize,
+    pub title: Vec<Inline>,
+    pub heading_span: Span,
+    /// From the heading to the end of the last child
+    pub span: Span,
+    pub blocks: Vec<Block>,
+    pub children: Vec<Section>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Document {
+    /// Blocks before the first heading
+    pub blocks: Vec<Block>,
+    pub sections: Vec<Section>,
+}
+
+impl Block {
+    fn inlines(&self) -> &[Inline] {
+        match self {
+            Block::Paragraph { inlines, .. } | Block::ListItem { inlines, .. } => inlines,
+            Block::Code { .. } | Block::Blank { .. } => &[],
+        }
+    }
+}
+
+impl Section {
+    /// The heading text without the `=` markers.
+    pub fn title_text(&self) -> String {
+        self.title
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => text.clone(),
+                Inline::Hashtag(tag) => format!("#{tag}"),
+                Inline::Link { text, target } => text.clone().unwrap_or_else(|| target.clone()),
+            })
+            .collect()
+    }
+
+    fn inlines(&self) -> Box<dyn Iterator<Item = &Inline> + '_> {
+        Box::new(
+            self.title
+                .iter()
+                .chain(self.blocks.iter().flat_map(|block| block.inlines()))
+                .chain(self.children.iter().flat_map(|child| child.inlines())),
+        )
+    }
+}
+
+impl Document {
+    /// Every section, depth first in document order.
+    pub fn sections(&self) -> Vec<&Section> {
+        fn walk<'a>(sections: &'a [Section], out: &mut Vec<&'a Section>) {
+            for section in sections {
+                out.push(section);
+                walk(&section.children, out);
+            }
+        }
+        let mut out = Vec::new();
+        walk(&self.sections, &mut out);
+        out
+    }
+
+    /// Hashtags anywhere outside code blocks, in order of appearance and
+    /// without duplicates.
+    pub fn hashtags(&self) -> Vec<&str> {
+        let mut tags: Vec<&str> = Vec::new();
+        let inlines = self
+            .blocks
+            .iter()
+            .flat_map(|block| block.inlines())
+            .chain(self.sections.iter().flat_map(|section| section.inlines()));
+        for inline in inlines {
+            if let Inline::Hashtag(tag) = inline {
+                if !tags.contains(&tag.as_str()) {
+                    tags.push(tag);
+                }
+            }
+        }
+        tags
+    }
+}
+
+/// A snippet's heading text, from its first heading.
+pub fn snippet_heading(snippet: &str) -> String {
+    parse(snippet)
+        .sections
+        .first()
+        .map(|section| section.title_text())
+        .unwrap_or_default()
+}
+
+//------------------------------------------------------------------------------
+// Line parsers
+//------------------------------------------------------------------------------
+
+/// What a single line is, before lines are grouped into blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Line {
+    Heading {
+        level: usize,
+        title: Vec<Inline>,
+    },
+    Fence {
+        info: String,
+    },
+    ListItem {
+        indent: usize,
+        marker: String,
+        inlines: Vec<Inline>,
+    },
+    Text(Vec<Inline>),
+    Blank,
+}
+
+fn inline_parser() -> impl Parser<char, Vec<Inline>, Error = Simple<char>> + Clone {
+    let space = filter(|c: &char| c.is_whitespace())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .map(Inline::Text);
+
+    let md_link = none_of("]\n")
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .delimited_by(just('['), just(']'))
+        .then(
+            none_of(")\n")
+                .repeated()
+                .at_least(1)
+                .collect::<String>()
+                .delimited_by(just('('), just(')')),
+        )
+        .map(|(text, target)| Inline::Link {
+            text: Some(text),
+            target,
+        });
+
+    let wiki_link = none_of("]\n")
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .delimited_by(just("[["), just("]]"))
+        .map(|target| Inline::Link { text: None, target });
+
+    let url = choice((just("https://"), just("http://")))
+        .then(
+            filter(|c: &char| !c.is_whitespace())
+                .repeated()
+                .at_least(1)
+                .collect::<String>(),
+        )
+        .map(|(scheme, rest)| Inline::Link {
+            text: None,
+            target: format!("{scheme}{rest}"),
+        });
+
+    // `#` then a letter or digit, so `# ` and `##` stay text
+    let tag_char = filter(|c: &char| c.is_alphanumeric() || matches!(c, '_' | '-' | '/'));
+    let hashtag = just('#')
+        .ignore_then(filter(|c: &char| c.is_alphanumeric()).chain(tag_char.repeated()))
+        .collect::<String>()
+        .map(Inline::Hashtag);
+
+    // anything else up to the next space, so `a#b` isn't a hashtag
+    let word = filter(|c: &char| !c.is_whitespace())
+        .repeated()
+        .at_least(1)
+        .collect::<String>()
+        .map(Inline::Text);
+
+    choice((space, md_link, wiki_link, url, hashtag, word))
+        .repeated()
+        .map(merge_text)
+}
+
+/// Join adjacent text runs.
+fn merge_text(inlines: Vec<Inline>) -> Vec<Inline> {
+    let mut out: Vec<Inline> = Vec::new();
+    for inline in inlines {
+        match (out.last_mut(), inline) {
+            (Some(Inline::Text(text)), Inline::Text(more)) => text.push_str(&more),
+            (_, inline) => out.push(inline),
+        }
+    }
+    out
+}
+
+fn line_parser() -> impl Parser<char, Line, Error = Simple<char>> {
+    let inlines = inline_parser();
+    let rest = any().repeated().collect::<String>();
+
+    // `=== Title`, optionally closed with `===`
+    let heading = just('=')
+        .repeated()
+        .at_least(1)
+        .then_ignore(one_of(" \t"))
+        .then(rest)
+        .try_map(move |(marks, title), span| {
+            let title = title.trim().trim_end_matches('=').trim_end();
+            let title = inlines
+                .parse(title)
+                .map_err(|_| Simple::custom(span, "invalid heading"))?;
+            Ok(Line::Heading {
+                level: marks.len(),
+                title,
+            })
+        });
+
+    let fence = just("```").ignore_then(rest).map(|info| Line::Fence {
+        info: info.trim().to_string(),
+    });
+
+    let indent = one_of(" \t").repeated().map(|indent| indent.len());
+    let marker = choice((
+        one_of("-*+").map(|c: char| c.to_string()),
+        text::digits(10)
+            .then_ignore(just('.'))
+            .map(|n: String| format!("{n}.")),
+    ));
+    let list_item = indent
+        .then(marker)
+        .then_ignore(one_of(" \t"))
+        .then(inline_parser())
+        .map(|((indent, marker), inlines)| Line::ListItem {
+            indent,
+            marker,
+            inlines,
+        });
+
+    let blank = one_of(" \t").repeated().then(end()).to(Line::Blank);
+    let text = inline_parser().map(Line::Text);
+
+    choice((blank, heading, fence, list_item, text)).then_ignore(end())
+}
+
+//------------------------------------------------------------------------------
+// Tree building
+//------------------------------------------------------------------------------
+
+/// Parse an mwk document. Parsing never fails: anything that isn't a
+/// heading, fence, list item or blank line is text.
+pub fn parse(source: &str) -> Document {
+    let parser = line_parser();
+    let mut document = Document::default();
+    // open sections, innermost last
+    let mut stack: Vec<Section> = Vec::new();
+    // an open code fence: (start, info, code)
+    let mut fence: Option<(usize, String, String)> = None;
+
+    let mut offset = 0;
+    for segment in source.split_inclusive('\n') {
+        let span = offset..offset + segment.len();
+        offset += segment.len();
+        let text = segment.trim_end_matches(['\n', '\r']);
+
+        if let Some((start, info, mut code)) = fence.take() {
+            if text.trim_start().starts_with("```") {
+                push_block(
+                    &mut document,
+                    &mut stack,
+                    Block::Code {
+                        span: start..span.end,
+                        info,
+                        code,
+                    },
+                );
+            } else {
+                code.push_str(segment);
+                fence = Some((start, info, code));
+            }
+            continue;
+        }
+
+        let line = parser
+            .parse(text)
+            .unwrap_or_else(|_| Line::Text(vec![Inline::Text(text.to_string())]));
+        match line {
+            Line::Heading { level, title } => {
+                close_sections(&mut document, &mut stack, level, span.start);
+                stack.push(Section {
+                    level,
+                    title,
+                    heading_span: span.clone(),
+                    span,
+                    blocks: Vec::new(),
+                    children: Vec::new(),
+                });
+            }
+            Line::Fence { info } => fence = Some((span.start, info, String::new())),
+            Line::ListItem {
+                indent,
+                marker,
+                inlines,
+            } => push_block(
+                &mut document,
+                &mut stack,
+                Block::ListItem {
+                    span,
+                    indent,
+                    marker,
+                    inlines,
+                },
+            ),
+            Line::Text(inlines) => {
+                let blocks = match stack.last_mut() {
+                    Some(section) => &mut section.blocks,
+                    None => &mut document.blocks,
+                };
+                // continue the paragraph on the previous line
+                if let Some(Block::Paragraph {
+                    span: previous,
+                    inlines: previous_inlines,
+                }) = blocks.last_mut()
+                {
+                    previous.end = span.end;
+                    previous_inlines.push(Inline::Text("\n".to_string()));
+                    previous_inlines.extend(inlines);
+                    let merged = merge_text(std::mem::take(previous_inlines));
+                    *previous_inlines = merged;
+                } else {
+                    blocks.push(Block::Paragraph { span, inlines });
+                }
+            }
+            Line::Blank => push_block(&mut document, &mut stack, Block::Blank { span }),
+        }
+    }
+
+    if let Some((start, info, code)) = fence {
+        push_block(
+            &mut document,
+            &mut stack,
+            Block::Code {
+                span: start..source.len(),
+                info,
+                code,
+            },
+        );
+    }
+    close_sections(&mut document, &mut stack, 0, source.len());
+    document
+}
+
+fn push_block(document: &mut Document, stack: &mut [Section], block: Block) {
+    match stack.last_mut() {
+        Some(section) => section.blocks.push(block),
+        None => document.blocks.push(block),
+    }
+}
+
+/// Close every open section at `level` or deeper, ending them at `end`.
+fn close_sections(document: &mut Document, stack: &mut Vec<Section>, level: usize, end: usize) {
+    while stack.last().is_some_and(|section| section.level >= level) {
+        let mut section = stack.pop().expect("checked above");
+        section.span.end = end;
+        match stack.last_mut() {
+            Some(parent) => parent.children.push(section),
+            None => document.sections.push(section),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The spans of everything in `document`, in order.
+    fn spans(document: &Document) -> Vec<Span> {
+        fn block_span(block: &Block) -> Span {
+            match block {
+                Block::Paragraph { span, .. }
+                | Block::ListItem { span, .. }
+                | Block::Code { span, .. }
+                | Block::Blank { span } => span.clone(),
+            }
+        }
+        fn section_spans(section: &Section, out: &mut Vec<Span>) {
+            out.push(section.heading_span.clone());
+            out.extend(section.blocks.iter().map(block_span));
+            for child in &section.children {
+                section_spans(child, out);
+            }
+            let end = out.last().map_or(section.span.start, |span| span.end);
+            assert_eq!(end, section.span.end, "section ends after its last child");
+        }
+        let mut out: Vec<Span> = document.blocks.iter().map(block_span).collect();
+        for section in &document.sections {
+            section_spans(section, &mut out);
+        }
+        out
+    }
+
+    /// Inlines written back as mwk.
+    fn render(inlines: &[Inline]) -> String {
+        inlines
+            .iter()
+            .map(|inline| match inline {
+                Inline::Text(text) => text.clone(),
+                Inline::Hashtag(tag) => format!("#{tag}"),
+                Inline::Link {
+                    text: Some(text),
+                    target,
+                } => format!("[{text}]({target})"),
+                Inline::Link { text: None, target } if target.contains("://") => target.clone(),
+                Inline::Link { text: None, target } => format!("[[{target}]]"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn spans_cover_the_source_in_order() {
+        let sources = [
+            "",
+            "no headings\njust text\n",
+            "intro\n\n=== one\ntext\n==== nested\n- item\n  1. sub\n=== two #tag\n",
+            "=== code\n```rust\n=== not a heading\n```\nafter\n",
+            "=== unclosed\n```\nruns to the end",
+            "=== crlf\r\nline\r\n\r\n",
+        ];
+        for source in sources {
+            let document = parse(source);
+            let mut end = 0;
+            for span in spans(&document) {
+                assert_eq!(span.start, end, "{source:?}: gap or overlap at {span:?}");
+                end = span.end;
+            }
+            assert_eq!(end, source.len(), "{source:?}: not all covered");
+        }
+    }
+
+    #[test]
+    fn inlines_round_trip() {
+        let lines = [
+            "plain words  with   spacing",
+            "#tag and #two-part/tag, but a#b and # and ## aren't",
+            "see [the docs](https://example.com/x) or [[Wiki Page]]",
+            "bare https://example.com/a?b=c#frag link",
+            "[unclosed link and ]( odd brackets",
+        ];
+        for line in lines {
+            let document = parse(&format!("{line}\n"));
+            let [Block::Paragraph { inlines, .. }] = &document.blocks[..] else {
+                panic!("{line:?} is one paragraph");
+            };
+            assert_eq!(render(inlines), line);
+        }
+    }
+
+    #[test]
+    fn headings_nest_by_level() {
+        let document = parse("== a\n=== b\n==== c\n=== d\n== e\n=== f ===\n");
+        let titles = |sections: &[Section]| -> Vec<String> {
+            sections.iter().map(Section::title_text).collect()
+        };
+        assert_eq!(titles(&document.sections), ["a", "e"]);
+        assert_eq!(titles(&document.sections[0].children), ["b", "d"]);
+        assert_eq!(titles(&document.sections[0].children[0].children), ["c"]);
+        // closing `=`s aren't part of the title
+        assert_eq!(titles(&document.sections[1].children), ["f"]);
+    }
+
+    #[test]
+    fn hashtags_skip_code_and_repeats() {
+        let document = parse("#top\n=== a #pin\n#top again\n```\n#incode\n```\n- #listed\n");
+        assert_eq!(document.hashtags(), ["top", "pin", "listed"]);
+    }
+}
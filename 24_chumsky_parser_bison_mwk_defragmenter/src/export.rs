@@ -0,0 +1,374 @@
+use crate::document::{Block, Document, Inline, Section};
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// An export format for `--to`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Format> {
+        match name {
+            "markdown" | "md" => Some(Format::Markdown),
+            "html" => Some(Format::Html),
+            _ => None,
+        }
+    }
+}
+
+pub fn export(document: &Document, format: Format, title: &str) -> String {
+    match format {
+        Format::Markdown => to_markdown(document),
+        Format::Html => to_html(document, title),
+    }
+}
+
+//------------------------------------------------------------------------------
+// CommonMark
+//------------------------------------------------------------------------------
+
+/// `=` headings become `#` ones, capped at six. Hashtags are kept as
+/// `#tag`, which CommonMark leaves as text and most note tools read as
+/// tags; bare URLs become autolinks and `[[wiki]]` links ordinary links.
+pub fn to_markdown(document: &Document) -> String {
+    let mut out = String::new();
+    markdown_blocks(&mut out, &document.blocks);
+    for section in &document.sections {
+        markdown_section(&mut out, section);
+    }
+    out
+}
+
+fn markdown_section(out: &mut String, section: &Section) {
+    let mut title = markdown_inlines(&section.title);
+    // trailing `#`s would be read as the heading's closing sequence
+    if let Some(closing) = title.strip_suffix('#') {
+        let at = closing.trim_end_matches('#').len();
+        title.insert(at, '\\');
+    }
+    let _ = writeln!(out, "{} {title}", "#".repeat(section.level.min(6)));
+    markdown_blocks(out, &section.blocks);
+    for child in &section.children {
+        markdown_section(out, child);
+    }
+}
+
+fn markdown_blocks(out: &mut String, blocks: &[Block]) {
+    for block in blocks {
+        match block {
+            Block::Paragraph { inlines, .. } => {
+                let text = markdown_inlines(inlines);
+                let lines: Vec<String> = text.split('\n').map(escape_line_start).collect();
+                let _ = writeln!(out, "{}", lines.join("\n"));
+            }
+            Block::ListItem {
+                indent,
+                marker,
+                inlines,
+                ..
+            } => {
+                let _ = writeln!(
+                    out,
+                    "{}{marker} {}",
+                    " ".repeat(*indent),
+                    escape_line_start(&markdown_inlines(inlines))
+                );
+            }
+            Block::Code { info, code, .. } => {
+                // a longer fence than any backtick run inside
+                let fence = "`".repeat(longest_backtick_run(code).max(2) + 1);
+                let newline = if code.is_empty() || code.ends_with('\n') {
+                    ""
+                } else {
+                    "\n"
+                };
+                let _ = writeln!(out, "{fence}{info}\n{code}{newline}{fence}");
+            }
+            Block::Blank { .. } => out.push('\n'),
+        }
+    }
+}
+
+fn markdown_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => markdown_escape(text),
+            Inline::Hashtag(tag) => format!("#{tag}"),
+            Inline::Link {
+                text: Some(text),
+                target,
+            } => format!("[{}]({target})", markdown_escape(text)),
+            Inline::Link { text: None, target } if is_url(target) => format!("<{target}>"),
+            Inline::Link { text: None, target } => format!("[{target}](<{target}>)"),
+        })
+        .collect()
+}
+
+/// Backslash-escape what CommonMark would read as emphasis, code, links
+/// or HTML anywhere in a line.
+fn markdown_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '`' | '*' | '_' | '[' | ']' | '<') {
+            out.push('\\');
+        }
+        out.push(c);
+    }
+    out
+}
+
+/// Escape what would start a heading, block quote, list item, thematic
+/// break, setext underline or fence at the start of a line: `# x`, `> x`,
+/// `- x`, `1. x`, `---`, `===` and `~~~`.
+fn escape_line_start(line: &str) -> String {
+    let rest = line.trim_start();
+    let indent = line.len() - rest.len();
+    let mut chars = rest.chars();
+    let Some(first) = chars.next() else {
+        return line.to_string();
+    };
+    let then_space = |after: &str| after.is_empty() || after.starts_with([' ', '\t']);
+
+    let at = match first {
+        '>' => Some(indent),
+        '#' if then_space(rest.trim_start_matches('#')) => Some(indent),
+        '-' | '+' if then_space(chars.as_str()) => Some(indent),
+        // thematic breaks and setext underlines, such as `---` or `= = =`
+        '-' | '=' if rest.chars().all(|c| c == first || c == ' ' || c == '\t') => Some(indent),
+        '~' if rest.starts_with("~~~") => Some(indent),
+        // `1.` or `1)`, escaping the punctuation rather than the number
+        '0'..='9' => {
+            let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+            let after = &rest[digits..];
+            (digits <= 9 && after.starts_with(['.', ')']) && then_space(&after[1..]))
+                .then_some(indent + digits)
+        }
+        _ => None,
+    };
+    match at {
+        Some(at) => format!("{}\\{}", &line[..at], &line[at..]),
+        None => line.to_string(),
+    }
+}
+
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+fn is_url(target: &str) -> bool {
+    target.starts_with("http://") || target.starts_with("https://")
+}
+
+//------------------------------------------------------------------------------
+// HTML
+//------------------------------------------------------------------------------
+
+const STYLE: &str = "body{font-family:sans-serif;max-width:50em;margin:auto;padding:1em}\
+nav ul{padding-left:1.2em}.tag{color:#06c}pre{background:#f4f4f4;padding:.5em;overflow:auto}";
+
+/// A standalone page with a table of contents linking every heading.
+/// Paragraph lines keep their line breaks, as notes are written a thought
+/// per line.
+pub fn to_html(document: &Document, title: &str) -> String {
+    let mut ids = Ids::default();
+    let mut toc = String::new();
+    let mut body = String::new();
+    html_blocks(&mut body, &document.blocks);
+    if !document.sections.is_empty() {
+        toc.push_str("<ul>\n");
+        for section in &document.sections {
+            html_section(&mut body, &mut toc, &mut ids, section);
+        }
+        toc.push_str("</ul>\n");
+    }
+
+    let title = escape(title);
+    format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>{STYLE}</style>\n</head>\n<body>\n<nav id=\"contents\">\n<h2>Contents</h2>\n\
+         {toc}</nav>\n<main>\n{body}</main>\n</body>\n</html>\n"
+    )
+}
+
+fn html_section(body: &mut String, toc: &mut String, ids: &mut Ids, section: &Section) {
+    let id = ids.unique(&section.title_text());
+    let level = section.level.min(6);
+    let title = html_inlines(&section.title);
+    let _ = writeln!(body, "<h{level} id=\"{id}\">{title}</h{level}>");
+    let _ = write!(
+        toc,
+        "<li><a href=\"#{id}\">{}</a>",
+        escape(&section.title_text())
+    );
+
+    html_blocks(body, &section.blocks);
+    if !section.children.is_empty() {
+        toc.push_str("\n<ul>\n");
+        for child in &section.children {
+            html_section(body, toc, ids, child);
+        }
+        toc.push_str("</ul>\n");
+    }
+    toc.push_str("</li>\n");
+}
+
+fn html_blocks(out: &mut String, blocks: &[Block]) {
+    // open lists, innermost last: (indent, closing tag)
+    let mut lists: Vec<(usize, &str)> = Vec::new();
+    for block in blocks {
+        let Block::ListItem {
+            indent,
+            marker,
+            inlines,
+            ..
+        } = block
+        else {
+            close_lists(out, &mut lists, 0);
+            match block {
+                Block::Paragraph { inlines, .. } => {
+                    let text = html_inlines(inlines).replace('\n', "<br>\n");
+                    let _ = writeln!(out, "<p>{text}</p>");
+                }
+                Block::Code { info, code, .. } => {
+                    let class = match info.split_whitespace().next() {
+                        Some(language) => format!(" class=\"language-{}\"", escape(language)),
+                        None => String::new(),
+                    };
+                    let _ = writeln!(out, "<pre><code{class}>{}</code></pre>", escape(code));
+                }
+                Block::ListItem { .. } | Block::Blank { .. } => {}
+            }
+            continue;
+        };
+
+        // deeper items nest inside the previous item, and a change of
+        // marker type starts a new list
+        let tag = if marker.ends_with('.') { "ol" } else { "ul" };
+        close_lists(out, &mut lists, indent + 1);
+        if lists
+            .last()
+            .is_some_and(|&(open, open_tag)| open == *indent && open_tag != tag)
+        {
+            close_lists(out, &mut lists, *indent);
+        }
+        match lists.last() {
+            Some(&(open, _)) if open == *indent => out.push_str("</li>\n"),
+            _ => {
+                let _ = writeln!(out, "<{tag}>");
+                lists.push((*indent, tag));
+            }
+        }
+        let _ = write!(out, "<li>{}", html_inlines(inlines));
+    }
+    close_lists(out, &mut lists, 0);
+}
+
+/// Close the lists indented at least `indent`.
+fn close_lists(out: &mut String, lists: &mut Vec<(usize, &str)>, indent: usize) {
+    while let Some(&(open, tag)) = lists.last() {
+        if open < indent {
+            break;
+        }
+        let _ = writeln!(out, "</li>\n</{tag}>");
+        lists.pop();
+    }
+}
+
+fn html_inlines(inlines: &[Inline]) -> String {
+    inlines
+        .iter()
+        .map(|inline| match inline {
+            Inline::Text(text) => escape(text),
+            Inline::Hashtag(tag) => format!("<span class=\"tag\">#{}</span>", escape(tag)),
+            Inline::Link { text, target } => format!(
+                "<a href=\"{}\">{}</a>",
+                escape(target),
+                escape(text.as_deref().unwrap_or(target))
+            ),
+        })
+        .collect()
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Heading anchors: the title lowercased with runs of anything else turned
+/// into `-`, numbered when repeated. Every id handed out is remembered, so
+/// a numbered one can't collide with a later title that slugs to the same.
+#[derive(Default)]
+struct Ids {
+    issued: HashSet<String>,
+}
+
+impl Ids {
+    fn unique(&mut self, title: &str) -> String {
+        let slug = title
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<_>>()
+            .join("-");
+        let slug = if slug.is_empty() {
+            "section".to_string()
+        } else {
+            slug
+        };
+        let mut id = slug.clone();
+        let mut n = 1;
+        while !self.issued.insert(id.clone()) {
+            n += 1;
+            id = format!("{slug}-{n}");
+        }
+        id
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::document;
+
+    #[test]
+    fn markdown_escapes_text_that_would_become_markup() {
+        let cases = [
+            ("=== n\n# not a heading\n", "\\# not a heading\n"),
+            ("=== n\n> not a quote\n", "\\> not a quote\n"),
+            ("=== n\n- - not nested\n", "- \\- not nested\n"),
+            ("=== n\n1) not ordered\n", "1\\) not ordered\n"),
+            ("=== n\nabove\n---\n", "above\n\\---\n"),
+            ("=== n\n*not emphasis* and a_b_c\n", "\\*not emphasis\\* and a\\_b\\_c\n"),
+            ("=== n\n`code` <b> [x]\n", "\\`code\\` \\<b> \\[x\\]\n"),
+            // hashtags stay tags
+            ("=== n\n#todo call back\n", "#todo call back\n"),
+        ];
+        for (mwk, expected) in cases {
+            let markdown = to_markdown(&document::parse(mwk));
+            let body = markdown.split_once('\n').map_or("", |(_, body)| body);
+            assert_eq!(body, expected, "{mwk:?}");
+        }
+    }
+
+    #[test]
+    fn markdown_escapes_closing_hashes_in_headings() {
+        let markdown = to_markdown(&document::parse("=== Use C#\n"));
+        assert_eq!(markdown.lines().next(), Some("### Use C\\#"));
+    }
+
+    #[test]
+    fn ids_never_repeat() {
+        let mut ids = Ids::default();
+        let issued: Vec<String> = ["a", "a", "a-2", "A", "", "?"]
+            .iter()
+            .map(|title| ids.unique(title))
+            .collect();
+        assert_eq!(issued, ["a", "a-2", "a-2-2", "a-3", "section", "section-2"]);
+    }
+}
@@ -0,0 +1,57 @@
+use chrono::Local;
+use similar::TextDiff;
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+/// Replace `path` with what `write` writes atomically, first copying the
+/// original to `<path>.<YYYYmmdd-HHMMSS>.bak` beside it. Returns the
+/// backup's path.
+pub fn replace(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<PathBuf> {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(format!(".{}.bak", Local::now().format("%Y%m%d-%H%M%S")));
+    let backup = PathBuf::from(backup);
+    fs::copy(path, &backup)?;
+    write_atomic(path, write)?;
+    Ok(backup)
+}
+
+/// Write to a temporary file in the same directory with `write` and rename
+/// it over `path`, so an interrupted run leaves either the old file or the
+/// new one, never a partial one.
+pub fn write_atomic(
+    path: &Path,
+    write: impl FnOnce(&mut dyn Write) -> io::Result<()>,
+) -> io::Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    let mut temp = NamedTempFile::new_in(dir)?;
+    let mut out = BufWriter::new(&mut temp);
+    write(&mut out)?;
+    out.flush()?;
+    drop(out);
+    temp.as_file().sync_all()?;
+    if let Ok(metadata) = fs::metadata(path) {
+        temp.as_file().set_permissions(metadata.permissions())?;
+    }
+    temp.persist(path).map_err(|e| e.error)?;
+    Ok(())
+}
+
+/// A unified diff from `old` to `new`, empty if they're equal.
+pub fn diff(path: &Path, old: &str, new: &str) -> String {
+    if old == new {
+        return String::new();
+    }
+    let name = path.display().to_string();
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .header(&name, &format!("{name} (defragmented)"))
+        .to_string()
+}
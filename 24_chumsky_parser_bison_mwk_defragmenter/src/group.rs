@@ -0,0 +1,131 @@
+use crate::document::{self, Inline, Section};
+use crate::order::{self, Rule};
+use std::collections::HashMap;
+
+// Group headings sit one level above the `===` snippets
+const GROUP_LEVEL: usize = 2;
+
+/// Parse a `--tag-order` list such as `work,#home,reading`.
+pub fn parse_tag_order(text: &str) -> Vec<String> {
+    text.split(',')
+        .map(|tag| tag.trim().trim_start_matches('#'))
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Collect snippets sharing a hashtag under one `== #tag` heading per tag.
+///
+/// Tags in `tag_order` come first in that order, then the rest by how many
+/// snippets carry them, then by first appearance. A snippet with several
+/// tags goes in the group of its first-ranked tag, and `#p0`..`#p9`
+/// priorities never form groups. Snippets within a group are sorted by
+/// `rules`. Untagged snippets keep their original order and come before
+/// the groups, so they don't end up under a tag's heading.
+///
+/// Group headings left by an earlier run, in `prefix` or at the end of a
+/// snippet, are removed and reused rather than repeated. Returns the new
+/// prefix and the pieces to print after it.
+pub fn group_by_tag(
+    prefix: &str,
+    snippets: Vec<String>,
+    tag_order: &[String],
+    rules: &[Rule],
+) -> (String, Vec<String>) {
+    let prefix = strip_group_headings(prefix);
+    let snippets: Vec<String> = snippets.iter().map(|s| strip_group_headings(s)).collect();
+    let tags: Vec<Vec<String>> = snippets
+        .iter()
+        .map(|snippet| {
+            document::parse(snippet)
+                .hashtags()
+                .into_iter()
+                .filter(|tag| !is_priority(tag))
+                .map(str::to_string)
+                .collect()
+        })
+        .collect();
+
+    //
+    // Rank the tags
+    //
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    let mut first_seen: Vec<&str> = Vec::new();
+    for tag in tags.iter().flatten() {
+        *counts.entry(tag).or_default() += 1;
+        if !first_seen.contains(&tag.as_str()) {
+            first_seen.push(tag);
+        }
+    }
+    let mut ranked: Vec<&str> = tag_order
+        .iter()
+        .map(String::as_str)
+        .filter(|tag| counts.contains_key(tag))
+        .collect();
+    let mut rest: Vec<&str> = first_seen
+        .into_iter()
+        .filter(|tag| !tag_order.iter().any(|listed| listed == tag))
+        .collect();
+    // stable, so ties keep their first appearance order
+    rest.sort_by_key(|tag| std::cmp::Reverse(counts[tag]));
+    ranked.extend(rest);
+
+    //
+    // Put each snippet in its best ranked tag's group
+    //
+    let mut groups: Vec<Vec<String>> = vec![Vec::new(); ranked.len()];
+    let mut untagged = Vec::new();
+    for (snippet, snippet_tags) in snippets.iter().zip(&tags) {
+        let group = snippet_tags
+            .iter()
+            .filter_map(|tag| ranked.iter().position(|ranked| ranked == tag))
+            .min();
+        match group {
+            Some(group) => groups[group].push(snippet.clone()),
+            None => untagged.push(snippet.clone()),
+        }
+    }
+
+    let mut pieces = untagged;
+    for (tag, mut group) in ranked.iter().zip(groups) {
+        if group.is_empty() {
+            continue;
+        }
+        order::sort_snippets(&mut group, rules);
+        pieces.push(format!("{} #{tag}\n", "=".repeat(GROUP_LEVEL)));
+        pieces.extend(group);
+    }
+    (prefix, pieces)
+}
+
+fn is_priority(tag: &str) -> bool {
+    tag.len() == 2 && tag.starts_with('p') && tag.as_bytes()[1].is_ascii_digit()
+}
+
+/// Whether `section` is a bare `== #tag` heading, as written by
+/// `group_by_tag`.
+fn is_group_heading(section: &Section) -> bool {
+    section.level == GROUP_LEVEL
+        && matches!(section.title.as_slice(), [Inline::Hashtag(_)])
+        && section.children.is_empty()
+        && section
+            .blocks
+            .iter()
+            .all(|block| matches!(block, document::Block::Blank { .. }))
+}
+
+fn strip_group_headings(text: &str) -> String {
+    let document = document::parse(text);
+    let mut out = String::with_capacity(text.len());
+    let mut from = 0;
+    for section in document
+        .sections()
+        .into_iter()
+        .filter(|s| is_group_heading(s))
+    {
+        out.push_str(&text[from..section.span.start]);
+        from = section.span.end;
+    }
+    out.push_str(&text[from..]);
+    out
+}
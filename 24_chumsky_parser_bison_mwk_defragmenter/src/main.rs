@@ -1,114 +1,440 @@
-use once_cell::sync::Lazy;
-use regex::Regex;
-use std::io::{self, Read};
+mod archive;
+mod batch;
+mod dedupe;
+mod document;
+mod export;
+mod files;
+mod group;
+mod merge;
+mod order;
+mod pin;
+mod stream;
 
-static CHUNK_START_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"^===\s").expect("valid chunk regex"));
-static HASHTAGGED: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n#").expect("valid hash chunk regex"));
+use chrono::{Duration, Local, NaiveDate};
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 
-fn is_chunk_start(line: &str) -> bool {
-    let trimmed = line.strip_suffix('\r').unwrap_or(line);
-    CHUNK_START_RE.is_match(trimmed)
+// Snippets are the `=== ` sections
+const SNIPPET_LEVEL: usize = 3;
+
+/// How to defragment, from the command line.
+struct Options {
+    rules: Vec<order::Rule>,
+    /// The tag order when grouping snippets by hashtag
+    grouping: Option<Vec<String>>,
+    dedupe: Option<dedupe::Mode>,
+    similarity: f64,
+    /// Archive snippets dated before this
+    archive_before: Option<NaiveDate>,
 }
 
-fn main() -> io::Result<()> {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input)?;
+impl Options {
+    /// Only sorting, which can be done by reordering byte ranges of the
+    /// input; the other options rewrite snippets, so need them as text.
+    fn streams(&self) -> bool {
+        self.grouping.is_none() && self.dedupe.is_none() && self.archive_before.is_none()
+    }
+}
 
+fn main() -> io::Result<()> {
     //
-    // Collect the snippets into a list
+    // Parse command-line arguments
     //
-    let (prefix, mut snippets, suffix) = extract_sections(&input);
+    let mut options = Options {
+        rules: order::default_rules(),
+        grouping: None,
+        dedupe: None,
+        similarity: dedupe::DEFAULT_SIMILARITY,
+        archive_before: None,
+    };
+    let mut files: Vec<PathBuf> = Vec::new();
+    let mut in_place = false;
+    let mut dry_run = false;
+    let mut export_format: Option<export::Format> = None;
+    let mut args = env::args().skip(1).peekable();
+    if args.peek().is_some_and(|arg| arg == "merge") {
+        let paths: Vec<PathBuf> = args.skip(1).map(PathBuf::from).collect();
+        return run_merge(&paths);
+    }
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // comma separated, e.g. `--order "tag:todo,priority,date desc"`
+            "--order" => {
+                let rules_text = expect_value(&arg, args.next())?;
+                options.rules = order::parse_rules(&rules_text).map_err(invalid_input)?;
+            }
+            // one rule per line
+            "--rules" => {
+                let file = expect_value(&arg, args.next())?;
+                let rules_text = fs::read_to_string(&file)
+                    .map_err(|e| io::Error::new(e.kind(), format!("Failed to read {file}: {e}")))?;
+                options.rules = order::parse_rules(&rules_text).map_err(invalid_input)?;
+            }
+            // tags ordered by frequency
+            "--group-by-tag" => {
+                options.grouping.get_or_insert_with(Vec::new);
+            }
+            // these tags first, e.g. `--tag-order work,home`; implies --group-by-tag
+            "--tag-order" => {
+                let tags = expect_value(&arg, args.next())?;
+                options.grouping = Some(group::parse_tag_order(&tags));
+            }
+            // drop, merge or report; pinned snippets are left alone
+            "--dedupe" => {
+                let mode = expect_value(&arg, args.next())?;
+                options.dedupe = Some(dedupe::Mode::from_name(&mode).ok_or_else(|| {
+                    invalid_input(format!(
+                        "--dedupe expects drop, merge or report, got {mode}"
+                    ))
+                })?);
+            }
+            // 0 to 1, where 1 only matches identical snippets
+            "--similarity" => {
+                let value = expect_value(&arg, args.next())?;
+                options.similarity = value
+                    .parse()
+                    .ok()
+                    .filter(|n| (0.0..=1.0).contains(n))
+                    .ok_or_else(|| {
+                        invalid_input(format!("--similarity expects 0 to 1, got {value}"))
+                    })?;
+            }
+            // rewrite the files, keeping a timestamped backup of each
+            "--in-place" | "-i" => in_place = true,
+            // write nothing, print a unified diff of what would change
+            "--diff" => dry_run = true,
+            // move snippets dated more than this many days ago to archive/YYYY.mwk
+            "--archive-older-than" => {
+                let value = expect_value(&arg, args.next())?;
+                let days: i64 = value.parse().map_err(|_| {
+                    invalid_input(format!("--archive-older-than expects days, got {value}"))
+                })?;
+                options.archive_before = Some(Local::now().date_naive() - Duration::days(days));
+            }
+            // print the notes as markdown or html instead of defragmenting
+            "--to" => {
+                let format = expect_value(&arg, args.next())?;
+                export_format = Some(export::Format::from_name(&format).ok_or_else(|| {
+                    invalid_input(format!("--to expects markdown or html, got {format}"))
+                })?);
+            }
+            _ if arg.starts_with('-') => {
+                return Err(invalid_input(format!("Unknown argument: {arg}")))
+            }
+            _ => files.push(PathBuf::from(arg)),
+        }
+    }
 
-    // sanity check
-    if !snippets.is_empty() && suffix.is_empty() {
-        return Err(io::Error::new(
-            io::ErrorKind::InvalidData,
-            "Nothing to defragment",
+    if let Some(format) = export_format {
+        if in_place || dry_run || files.iter().any(|file| file.is_dir()) {
+            return Err(invalid_input(
+                "--to prints to stdout, so takes neither --in-place, --diff nor directories"
+                    .to_string(),
+            ));
+        }
+        return export_files(&files, format);
+    }
+
+    if options.archive_before.is_some() && (files.is_empty() || !(in_place || dry_run)) {
+        return Err(invalid_input(
+            "--archive-older-than needs files and --in-place or --diff".to_string(),
         ));
     }
 
-    if snippets.is_empty() {
-        // no defragmentation needed
-        print!("{prefix}{suffix}");
+    if files.is_empty() {
+        if in_place {
+            return Err(invalid_input("--in-place needs files to edit".to_string()));
+        }
+        let input = stream::stdin()?;
+        let defragmented = defragment_bytes(&input, &options, None)?;
+        if dry_run {
+            let diff = files::diff(
+                Path::new("-"),
+                &String::from_utf8_lossy(&input),
+                &defragmented.text(&input),
+            );
+            print!("{diff}");
+        } else {
+            let mut out = BufWriter::new(io::stdout().lock());
+            defragmented.write_to(&input, &mut out)?;
+            out.flush()?;
+        }
         return Ok(());
     }
 
-    // sort snippets: hashtagged first
-    snippets.sort_by_key(|snippet| !HASHTAGGED.is_match(snippet));
+    // directories are processed in batch, with a report rather than output
+    if files.iter().any(|file| file.is_dir()) {
+        let files = batch::mwk_files(&files)?;
+        if !batch::run(&files, &options, in_place, dry_run) {
+            process::exit(1);
+        }
+        return Ok(());
+    }
 
-    //
-    // Finally, print the whole mwk file defragmented
-    //
-    print!("{prefix}");
-    for snippet in &snippets {
-        print!("{snippet}");
+    for file in &files {
+        let with_name = |e: io::Error| io::Error::new(e.kind(), format!("{}: {e}", file.display()));
+        let input = stream::open(file).map_err(with_name)?;
+        let defragmented = defragment_bytes(&input, &options, Some(file)).map_err(with_name)?;
+        for archived in &defragmented.archived {
+            eprintln!(
+                "{}: {} `{}` to {}",
+                file.display(),
+                if dry_run { "would archive" } else { "archived" },
+                archived.heading(),
+                archived.file().display()
+            );
+        }
+        if dry_run {
+            let diff = files::diff(
+                file,
+                &String::from_utf8_lossy(&input),
+                &defragmented.text(&input),
+            );
+            print!("{diff}");
+        } else if in_place {
+            if !defragmented.unchanged(&input) {
+                archive::write(file, &defragmented.archived).map_err(with_name)?;
+                let backup = files::replace(file, |out| defragmented.write_to(&input, out))
+                    .map_err(with_name)?;
+                eprintln!(
+                    "{}: defragmented, backup in {}",
+                    file.display(),
+                    backup.display()
+                );
+            }
+        } else {
+            let mut out = BufWriter::new(io::stdout().lock());
+            defragmented.write_to(&input, &mut out)?;
+            out.flush()?;
+        }
     }
-    print!("{suffix}");
 
     Ok(())
 }
 
-fn extract_sections(input: &str) -> (String, Vec<String>, String) {
-    //
-    // Find the starting line numbers for each snippet
-    //
+/// Merge `base ours theirs` into `ours` as a git merge driver, exiting 1
+/// if there are conflicts. An optional fourth argument names the file in
+/// messages, as git's temporary files mean little.
+fn run_merge(paths: &[PathBuf]) -> io::Result<()> {
+    let (base, ours, theirs, name) = match paths {
+        [base, ours, theirs] => (base, ours, theirs, ours),
+        [base, ours, theirs, name] => (base, ours, theirs, name),
+        _ => {
+            return Err(invalid_input(
+                "Usage: mwk_defragment merge BASE OURS THEIRS [PATH]".to_string(),
+            ))
+        }
+    };
+    let read = |path: &PathBuf| {
+        fs::read_to_string(path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", name.display())))
+    };
+    let (merged, conflicts) = merge::merge(&read(base)?, &read(ours)?, &read(theirs)?);
+    files::write_atomic(ours, |out| out.write_all(merged.as_bytes()))?;
+    if conflicts > 0 {
+        eprintln!("{}: {conflicts} conflicting snippets", name.display());
+        process::exit(1);
+    }
+    Ok(())
+}
 
-    let mut chunk_starts: Vec<usize> = Vec::new();
-    let mut offset = 0usize;
+/// Print each file, or stdin if there are none, in `format`.
+fn export_files(files: &[PathBuf], format: export::Format) -> io::Result<()> {
+    if files.is_empty() {
+        let mut input = String::new();
+        io::stdin().read_to_string(&mut input)?;
+        print!("{}", export_document(&input, "Notes", format));
+    }
+    for file in files {
+        let input = fs::read_to_string(file)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", file.display())))?;
+        let name = file.file_stem().unwrap_or_default().to_string_lossy();
+        print!("{}", export_document(&input, &name, format));
+    }
+    Ok(())
+}
 
-    for segment in input.split_inclusive('\n') {
-        let mut line = segment.strip_suffix('\n').unwrap_or(segment);
-        line = line.strip_suffix('\r').unwrap_or(line);
+/// Titled by the first `=` heading, or `name` if there isn't one.
+fn export_document(input: &str, name: &str, format: export::Format) -> String {
+    let document = document::parse(input);
+    let title = document
+        .sections
+        .iter()
+        .find(|section| section.level == 1)
+        .map(|section| section.title_text())
+        .unwrap_or_else(|| name.to_string());
+    export::export(&document, format, &title)
+}
+
+/// A defragmented mwk file.
+struct Defragmented {
+    output: Output,
+    snippets: usize,
+    /// Snippets no longer in their original order, or removed
+    moved: usize,
+    /// Snippets taken out for the archive
+    archived: Vec<archive::Archived>,
+}
+
+/// The defragmented file: rewritten text, or the input's pieces reordered.
+enum Output {
+    Text(String),
+    Pieces(stream::Plan),
+}
 
-        if is_chunk_start(line) {
-            chunk_starts.push(offset);
+impl Defragmented {
+    /// Whether the output is the same as `input`, so needn't be written.
+    fn unchanged(&self, input: &[u8]) -> bool {
+        match &self.output {
+            Output::Text(text) => text.as_bytes() == input,
+            Output::Pieces(plan) => plan.moved == 0,
         }
+    }
 
-        offset += segment.len();
+    fn write_to(&self, input: &[u8], out: &mut dyn Write) -> io::Result<()> {
+        match &self.output {
+            Output::Text(text) => out.write_all(text.as_bytes()),
+            Output::Pieces(plan) => plan.write_to(input, out),
+        }
     }
 
-    if chunk_starts.is_empty() {
-        return (input.to_string(), Vec::new(), String::new());
+    /// The output as text for a diff, with anything not UTF-8 replaced.
+    fn text<'a>(&'a self, input: &[u8]) -> Cow<'a, str> {
+        match &self.output {
+            Output::Text(text) => Cow::Borrowed(text),
+            Output::Pieces(plan) => Cow::Owned(plan.to_text(input)),
+        }
     }
+}
 
+/// Defragment an mwk file's bytes. Plain sorting streams, copying snippets
+/// from `input` as they're written, so it copes with files of any size or
+/// encoding; the other options need `input` to be UTF-8.
+fn defragment_bytes(
+    input: &[u8],
+    options: &Options,
+    path: Option<&Path>,
+) -> io::Result<Defragmented> {
+    if options.streams() {
+        let plan = stream::plan(input, &options.rules);
+        return Ok(Defragmented {
+            snippets: plan.snippets,
+            moved: plan.moved,
+            archived: Vec::new(),
+            output: Output::Pieces(plan),
+        });
+    }
+    let input = std::str::from_utf8(input).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("--group-by-tag, --dedupe and --archive-older-than need UTF-8 text: {e}"),
+        )
+    })?;
+    Ok(defragment(input, options, path))
+}
+
+/// Defragment a whole mwk file. The text before the first snippet stays
+/// put, as do snippets tagged `#pin`, `#pin-top` or `#pin-bottom`, which
+/// are also left out of deduplication. `path` is where the file came from,
+/// for dating snippets with `git blame`.
+fn defragment(input: &str, options: &Options, path: Option<&Path>) -> Defragmented {
     //
-    // Find the start and end line numbers of each snippet
+    // Collect the snippets into a list
     //
-    let mut chunk_ranges: Vec<(usize, usize)> = Vec::with_capacity(chunk_starts.len());
-    for (idx, &start) in chunk_starts.iter().enumerate() {
-        let end = if idx + 1 < chunk_starts.len() {
-            // store the start of the next snippet as the end of this one
-            chunk_starts[idx + 1]
-        } else {
-            // last snippet ending line is the length of the file
-            input.len()
+    let (mut prefix, mut snippets) = extract_sections(input);
+    let snippet_count = snippets.len();
+
+    let archived = match options.archive_before {
+        Some(cutoff) => {
+            let blame = path.and_then(archive::blame_dates);
+            let first_line = prefix.lines().count();
+            archive::take_old(&mut snippets, first_line, blame.as_deref(), cutoff)
+        }
+        None => Vec::new(),
+    };
+
+    if snippets.is_empty() {
+        // no defragmentation needed
+        return Defragmented {
+            output: Output::Text(prefix),
+            snippets: snippet_count,
+            moved: snippet_count,
+            archived,
         };
-        chunk_ranges.push((start, end));
     }
 
-    //
-    // Do not move the top chunk before the first heading-3 snippet anywhere
-    //
-    let prefix_end = chunk_starts[0];
-    let prefix = input[..prefix_end].to_string();
+    // the last snippet may move, so give it a line ending to move with
+    let missing_newline = !input.ends_with('\n');
+    if missing_newline {
+        snippets.last_mut().expect("not empty").push('\n');
+    }
+    let original = snippets.clone();
+
+    let snippets = pin::arrange_unpinned(
+        snippets,
+        |s| Cow::Borrowed(s),
+        |mut snippets| {
+            // pinned snippets are never dropped or merged away
+            if let Some(mode) = options.dedupe {
+                dedupe::dedupe(&mut snippets, mode, options.similarity);
+            }
+            match &options.grouping {
+                Some(tag_order) => {
+                    (prefix, snippets) =
+                        group::group_by_tag(&prefix, snippets, tag_order, &options.rules);
+                }
+                // sort snippets, hashtagged first unless other rules were given
+                None => order::sort_snippets(&mut snippets, &options.rules),
+            }
+            snippets
+        },
+    );
 
     //
-    // Extract all chunks except the first and last
+    // Finally, put the whole mwk file back together
     //
-    let mut chunks: Vec<_> = chunk_ranges
-        .iter()
-        .map(|&(start, end)| input[start..end].to_string())
-        .collect();
+    let mut output = prefix;
+    for snippet in &snippets {
+        output.push_str(snippet);
+    }
+    if missing_newline {
+        output.pop();
+    }
 
-    if chunks.is_empty() {
-        return (prefix, Vec::new(), String::new());
+    // snippets outside the longest run left in order have moved
+    let in_order = similar::capture_diff_slices(similar::Algorithm::Myers, &original, &snippets)
+        .iter()
+        .filter_map(|op| match op {
+            similar::DiffOp::Equal { len, .. } => Some(len),
+            _ => None,
+        })
+        .sum::<usize>();
+    Defragmented {
+        output: Output::Text(output),
+        snippets: snippet_count,
+        moved: snippet_count - in_order,
+        archived,
     }
+}
 
-    //
-    // Do not move the bottom chunk after the last heading-3 snippet anywhere
-    //
-    let suffix = chunks.pop().unwrap();
+fn expect_value(arg: &str, value: Option<String>) -> io::Result<String> {
+    value.ok_or_else(|| invalid_input(format!("{arg} expects a value")))
+}
+
+fn invalid_input(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
 
-    (prefix, chunks, suffix)
+fn extract_sections(input: &str) -> (String, Vec<String>) {
+    // snippet boundaries always start a line, so they're char boundaries too
+    let (prefix, snippets) = stream::snippet_ranges(input.as_bytes());
+    let snippets = snippets
+        .into_iter()
+        .map(|range| input[range].to_string())
+        .collect();
+    (input[prefix].to_string(), snippets)
 }
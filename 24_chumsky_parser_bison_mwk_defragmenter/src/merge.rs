@@ -0,0 +1,387 @@
+use crate::dedupe;
+use crate::document;
+use crate::extract_sections;
+use std::borrow::Cow;
+use std::collections::HashMap;
+
+/// A snippet's identity across versions: its heading, and which of the
+/// base's snippets with that heading it is. Snippets a side added are
+/// numbered after the base's.
+type Key = (String, usize);
+
+/// One version of a notes file split into snippets.
+struct Version {
+    prefix: String,
+    snippets: Vec<(Key, String)>,
+}
+
+impl Version {
+    /// The common ancestor, its snippets with the same heading numbered in
+    /// order.
+    fn base(text: &str) -> Version {
+        let (prefix, snippets) = split(text);
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let snippets = snippets
+            .into_iter()
+            .map(|(heading, snippet)| {
+                let count = seen.entry(heading.clone()).or_default();
+                *count += 1;
+                ((heading, *count), snippet)
+            })
+            .collect();
+        Version { prefix, snippets }
+    }
+
+    /// One side of the merge. Its snippets with the same heading are
+    /// aligned with the base's by content, so adding or removing one of
+    /// them doesn't change which base snippet the others are.
+    fn side(text: &str, base: &Version) -> Version {
+        let (prefix, snippets) = split(text);
+        let mut groups: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (index, (heading, _)) in snippets.iter().enumerate() {
+            groups.entry(heading).or_default().push(index);
+        }
+
+        let mut keys: Vec<Option<Key>> = vec![None; snippets.len()];
+        for (heading, indices) in groups {
+            let originals: Vec<&(Key, String)> = base
+                .snippets
+                .iter()
+                .filter(|((base_heading, _), _)| base_heading == heading)
+                .collect();
+            let texts: Vec<&str> = indices.iter().map(|&i| snippets[i].1.as_str()).collect();
+            let base_texts: Vec<&str> = originals.iter().map(|(_, s)| s.as_str()).collect();
+            for (i, j) in align(&texts, &base_texts) {
+                keys[indices[i]] = Some(originals[j].0.clone());
+            }
+            let mut count = originals.len();
+            for &index in &indices {
+                if keys[index].is_none() {
+                    count += 1;
+                    keys[index] = Some((heading.to_string(), count));
+                }
+            }
+        }
+
+        let snippets = keys
+            .into_iter()
+            .zip(snippets)
+            .map(|(key, (_, snippet))| (key.expect("every snippet keyed"), snippet))
+            .collect();
+        Version { prefix, snippets }
+    }
+
+    fn get(&self, key: &Key) -> Option<&str> {
+        self.snippets
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, snippet)| snippet.as_str())
+    }
+}
+
+/// `text`'s prefix and its snippets, each with its heading.
+fn split(text: &str) -> (String, Vec<(String, String)>) {
+    let (prefix, snippets) = extract_sections(text);
+    let snippets = snippets
+        .into_iter()
+        .map(|mut snippet| {
+            // every snippet ends its line, so any of them can go last
+            if !snippet.ends_with('\n') {
+                snippet.push('\n');
+            }
+            (document::snippet_heading(&snippet), snippet)
+        })
+        .collect();
+    (prefix, snippets)
+}
+
+/// Pair up `a` and `b` in order, as many as possible and of those the most
+/// alike, as index pairs. The lists are the few snippets sharing a heading,
+/// so comparing every pair is cheap.
+fn align(a: &[&str], b: &[&str]) -> Vec<(usize, usize)> {
+    let a: Vec<String> = a.iter().map(|s| dedupe::normalise(s)).collect();
+    let b: Vec<String> = b.iter().map(|s| dedupe::normalise(s)).collect();
+    // a pair counts 1 for matching at all, plus its similarity as tie-break
+    let weight = |i: usize, j: usize| 1.0 + dedupe::similarity(&a[i], &b[j]);
+
+    // best[i][j]: the best alignment of a[i..] with b[j..]
+    let mut best = vec![vec![0.0; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            best[i][j] = (weight(i, j) + best[i + 1][j + 1])
+                .max(best[i + 1][j])
+                .max(best[i][j + 1]);
+        }
+    }
+
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if best[i][j] == best[i + 1][j] {
+            i += 1;
+        } else if best[i][j] == best[i][j + 1] {
+            j += 1;
+        } else {
+            pairs.push((i, j));
+            i += 1;
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// The result of one snippet's three-way merge.
+enum Merged<'a> {
+    Keep(Cow<'a, str>),
+    Drop,
+    Conflict {
+        ours: Option<&'a str>,
+        theirs: Option<&'a str>,
+    },
+}
+
+/// Three-way merge of `ours` and `theirs`, which both come from `base`, one
+/// snippet at a time. Snippets are matched by heading, and ones sharing a
+/// heading by their order and content. A snippet added, edited or removed
+/// on one side takes that side's version, snippets added on both sides are
+/// all kept, and conflict markers are only written around a snippet edited
+/// differently on both sides, or edited on one and removed on the other. Snippets keep our order, with ones only they added
+/// placed after the snippet they follow in theirs. Returns the merged text
+/// and the number of conflicts.
+///
+/// As a git merge driver, in `.gitattributes`:
+///
+/// ```text
+/// *.mwk merge=mwk
+/// ```
+///
+/// and in `.git/config`:
+///
+/// ```text
+/// [merge "mwk"]
+///     name = mwk snippet merge
+///     driver = mwk_defragment merge %O %A %B %P
+/// ```
+pub fn merge(base: &str, ours: &str, theirs: &str) -> (String, usize) {
+    let base = Version::base(base);
+    let (ours, theirs) = (Version::side(ours, &base), Version::side(theirs, &base));
+    let mut conflicts = 0;
+    let mut out = String::new();
+
+    //
+    // The text before the first snippet, as a whole
+    //
+    match merge_one(Some(&base.prefix), Some(&ours.prefix), Some(&theirs.prefix)) {
+        Merged::Keep(prefix) => out.push_str(&prefix),
+        Merged::Drop => {}
+        Merged::Conflict { ours, theirs } => {
+            conflicts += 1;
+            push_conflict(&mut out, ours, theirs);
+        }
+    }
+
+    //
+    // Our snippets in our order, with theirs-only ones slotted in after
+    // their predecessor
+    //
+    let mut order: Vec<&Key> = ours.snippets.iter().map(|(key, _)| key).collect();
+    let mut previous: Option<&Key> = None;
+    for (key, _) in &theirs.snippets {
+        if !order.contains(&key) {
+            let at = previous
+                .and_then(|previous| order.iter().position(|k| *k == previous))
+                .map_or(0, |at| at + 1);
+            order.insert(at, key);
+        }
+        previous = Some(key);
+    }
+    // snippets we removed that they edited need a place for their conflict
+    for (key, _) in &base.snippets {
+        if !order.contains(&key) {
+            order.push(key);
+        }
+    }
+
+    for key in order {
+        match merge_one(base.get(key), ours.get(key), theirs.get(key)) {
+            Merged::Keep(snippet) => out.push_str(&snippet),
+            Merged::Drop => {}
+            Merged::Conflict { ours, theirs } => {
+                conflicts += 1;
+                push_conflict(&mut out, ours, theirs);
+            }
+        }
+    }
+    (out, conflicts)
+}
+
+fn merge_one<'a>(
+    base: Option<&'a str>,
+    ours: Option<&'a str>,
+    theirs: Option<&'a str>,
+) -> Merged<'a> {
+    let take =
+        |side: Option<&'a str>| side.map_or(Merged::Drop, |s| Merged::Keep(Cow::Borrowed(s)));
+    if ours == theirs {
+        take(ours)
+    } else if ours == base {
+        take(theirs)
+    } else if theirs == base {
+        take(ours)
+    } else if base.is_none() {
+        // added on both sides with different text; keep both
+        match (ours, theirs) {
+            (Some(ours), Some(theirs)) => Merged::Keep(Cow::Owned(format!("{ours}{theirs}"))),
+            _ => unreachable!("one side differs from a missing base"),
+        }
+    } else {
+        Merged::Conflict { ours, theirs }
+    }
+}
+
+fn push_conflict(out: &mut String, ours: Option<&str>, theirs: Option<&str>) {
+    out.push_str("<<<<<<< ours\n");
+    push_lines(out, ours.unwrap_or_default());
+    out.push_str("=======\n");
+    push_lines(out, theirs.unwrap_or_default());
+    out.push_str(">>>>>>> theirs\n");
+}
+
+/// `text`, ending its last line so a marker can follow.
+fn push_lines(out: &mut String, text: &str) {
+    out.push_str(text);
+    if !text.is_empty() && !text.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str = "intro\n=== a\none\n=== b\ntwo\n=== c\nthree\n";
+
+    #[test]
+    fn one_sided_changes_merge_cleanly() {
+        let ours = "intro\n=== a\none, edited\n=== b\ntwo\n=== c\nthree\n";
+        let theirs = "intro\n=== a\none\n=== c\nthree\n=== d\nfour\n";
+        assert_eq!(
+            merge(BASE, ours, theirs),
+            (
+                "intro\n=== a\none, edited\n=== c\nthree\n=== d\nfour\n".to_string(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn identical_sides_merge_to_themselves() {
+        let both = "new intro\n=== a\none\n=== c\nthree\n";
+        assert_eq!(merge(BASE, both, both), (both.to_string(), 0));
+    }
+
+    #[test]
+    fn different_edits_conflict() {
+        let ours = "intro\n=== a\none\n=== b\nours\n=== c\nthree\n";
+        let theirs = "intro\n=== a\none\n=== b\ntheirs\n=== c\nthree\n";
+        let expected = "intro\n=== a\none\n\
+                        <<<<<<< ours\n=== b\nours\n=======\n=== b\ntheirs\n>>>>>>> theirs\n\
+                        === c\nthree\n";
+        assert_eq!(merge(BASE, ours, theirs), (expected.to_string(), 1));
+    }
+
+    #[test]
+    fn edit_against_removal_conflicts() {
+        let ours = "intro\n=== a\none\n=== c\nthree\n";
+        let theirs = "intro\n=== a\none\n=== b\ntwo, edited\n=== c\nthree\n";
+        let expected = "intro\n=== a\none\n\
+                        <<<<<<< ours\n=======\n=== b\ntwo, edited\n>>>>>>> theirs\n\
+                        === c\nthree\n";
+        assert_eq!(merge(BASE, ours, theirs), (expected.to_string(), 1));
+    }
+
+    #[test]
+    fn prefix_conflicts_as_a_whole() {
+        let (merged, conflicts) = merge(
+            BASE,
+            &BASE.replace("intro", "ours"),
+            &BASE.replace("intro", "theirs"),
+        );
+        assert_eq!(conflicts, 1);
+        assert!(merged.starts_with("<<<<<<< ours\nours\n=======\ntheirs\n>>>>>>> theirs\n=== a\n"));
+    }
+
+    #[test]
+    fn both_sides_adding_keeps_both() {
+        let ours = format!("{BASE}=== d\nours\n");
+        let theirs = format!("{BASE}=== d\ntheirs\n");
+        assert_eq!(
+            merge(BASE, &ours, &theirs),
+            (format!("{BASE}=== d\nours\n=== d\ntheirs\n"), 0)
+        );
+    }
+
+    #[test]
+    fn theirs_only_snippets_follow_their_predecessor() {
+        let ours = "intro\n=== c\nthree\n=== a\none\n=== b\ntwo\n";
+        let theirs = "intro\n=== a\none\n=== new\nfrom them\n=== b\ntwo\n=== c\nthree\n";
+        assert_eq!(
+            merge(BASE, ours, theirs).0,
+            "intro\n=== c\nthree\n=== a\none\n=== new\nfrom them\n=== b\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn repeated_headings_are_told_apart() {
+        let base = "=== todo\nfirst\n=== todo\nsecond\n";
+        let ours = "=== todo\nfirst, done\n=== todo\nsecond\n";
+        let theirs = "=== todo\nfirst\n=== todo\nsecond, done\n";
+        assert_eq!(
+            merge(base, ours, theirs),
+            (
+                "=== todo\nfirst, done\n=== todo\nsecond, done\n".to_string(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn repeated_heading_added_in_front_keeps_the_others() {
+        let base = "=== todo\nfirst\n=== todo\nsecond\n";
+        let ours = "=== todo\nzeroth\n=== todo\nfirst\n=== todo\nsecond\n";
+        let theirs = "=== todo\nfirst\n=== todo\nsecond, done\n";
+        assert_eq!(
+            merge(base, ours, theirs),
+            (
+                "=== todo\nzeroth\n=== todo\nfirst\n=== todo\nsecond, done\n".to_string(),
+                0
+            )
+        );
+        // and the other way round
+        assert_eq!(
+            merge(base, theirs, ours),
+            (
+                "=== todo\nzeroth\n=== todo\nfirst\n=== todo\nsecond, done\n".to_string(),
+                0
+            )
+        );
+    }
+
+    #[test]
+    fn align_prefers_more_then_closer_pairs() {
+        assert_eq!(
+            align(&["zeroth", "first", "second"], &["first", "second"]),
+            [(1, 0), (2, 1)]
+        );
+        assert_eq!(align(&["rewritten"], &["first"]), [(0, 0)]);
+        assert_eq!(align(&["second, done"], &["first", "second"]), [(0, 1)]);
+    }
+
+    #[test]
+    fn missing_final_newline_still_merges() {
+        let ours = "intro\n=== a\none\n=== b\ntwo\n=== c\nthree, edited";
+        let (merged, conflicts) = merge(BASE, ours, BASE);
+        assert_eq!(conflicts, 0);
+        assert!(merged.ends_with("=== c\nthree, edited\n"));
+    }
+}
@@ -0,0 +1,211 @@
+use crate::document::{self, Document};
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+static HASHTAGGED: Lazy<Regex> = Lazy::new(|| Regex::new(r"\n#").expect("valid hash chunk regex"));
+static DATE_RE: Lazy<Regex> = Lazy::new(|| {
+    Regex::new(r"\b([0-9]{4})[-/.]([0-9]{2})[-/.]([0-9]{2})\b").expect("valid date regex")
+});
+
+/// One sort key. Snippets are compared by each rule in turn and keep their
+/// original order when every rule ties.
+#[derive(Debug, Clone)]
+pub struct Rule {
+    key: Key,
+    descending: bool,
+}
+
+#[derive(Debug, Clone)]
+enum Key {
+    /// Contains a line starting with `#` (the original, default ordering)
+    Hashtagged,
+    /// Contains the given hashtag
+    Tag(String),
+    /// `#p1` (first) to `#p9`, untagged snippets after
+    Priority,
+    /// Alphabetically by heading text, ignoring case
+    Heading,
+    /// By the `YYYY-MM-DD` date in the heading, undated snippets after
+    Date,
+    /// By number of lines
+    Length,
+}
+
+/// The ordering used when no rules are given.
+pub fn default_rules() -> Vec<Rule> {
+    vec![Rule {
+        key: Key::Hashtagged,
+        descending: false,
+    }]
+}
+
+/// Parse rules such as `tag:todo`, `priority`, `heading`, `date desc` or
+/// `length asc`, one per line in a rules file (blank lines and `# ` comments
+/// skipped) or comma separated on the command line.
+pub fn parse_rules(text: &str) -> Result<Vec<Rule>, String> {
+    text.split([',', '\n'])
+        .map(str::trim)
+        .filter(|rule| !rule.is_empty() && !rule.starts_with("# "))
+        .map(parse_rule)
+        .collect()
+}
+
+fn parse_rule(text: &str) -> Result<Rule, String> {
+    let mut words = text.split_whitespace();
+    let name = words.next().unwrap_or_default();
+    let descending = match words.next() {
+        None | Some("asc") => false,
+        Some("desc") => true,
+        Some(other) => {
+            return Err(format!(
+                "rule `{text}`: expected asc or desc, got `{other}`"
+            ))
+        }
+    };
+    if let Some(extra) = words.next() {
+        return Err(format!("rule `{text}`: unexpected `{extra}`"));
+    }
+
+    let key = match name.split_once(':') {
+        Some(("tag", tag)) => {
+            let tag = tag.trim_start_matches('#');
+            if tag.is_empty() {
+                return Err(format!("rule `{text}`: tag name is empty"));
+            }
+            Key::Tag(tag.to_string())
+        }
+        Some(_) => return Err(format!("unknown rule `{text}`")),
+        None => match name {
+            "hashtagged" => Key::Hashtagged,
+            "priority" => Key::Priority,
+            "heading" => Key::Heading,
+            "date" => Key::Date,
+            "length" => Key::Length,
+            _ => return Err(format!("unknown rule `{text}`")),
+        },
+    };
+    Ok(Rule { key, descending })
+}
+
+/// What the rules compare a snippet by, worked out once per snippet.
+struct Keys {
+    hashtagged: bool,
+    tags: Vec<String>,
+    heading: String,
+    date: Option<(u32, u32, u32)>,
+    lines: usize,
+}
+
+impl Keys {
+    /// Only parses `text` if the rules need its tags or heading, as that's
+    /// most of the time spent on a large file.
+    fn of(text: &str, rules: &[Rule]) -> Keys {
+        let parsed = rules
+            .iter()
+            .any(|rule| !matches!(rule.key, Key::Hashtagged | Key::Length))
+            .then(|| document::parse(text));
+        let heading = parsed.as_ref().map(heading).unwrap_or_default();
+        Keys {
+            hashtagged: HASHTAGGED.is_match(text),
+            tags: parsed
+                .iter()
+                .flat_map(Document::hashtags)
+                .map(str::to_string)
+                .collect(),
+            date: heading_date(&heading),
+            heading,
+            lines: text.lines().count(),
+        }
+    }
+}
+
+/// Stable sort by the rules in order.
+pub fn sort_snippets(snippets: &mut Vec<String>, rules: &[Rule]) {
+    sort_by_text(snippets, rules, |snippet| Cow::Borrowed(snippet));
+}
+
+/// Stable sort of anything standing for a snippet, such as its byte range,
+/// by the rules applied to the snippet's `text`.
+pub fn sort_by_text<T>(items: &mut Vec<T>, rules: &[Rule], text: impl Fn(&T) -> Cow<'_, str>) {
+    let mut keyed: Vec<(Keys, T)> = items
+        .drain(..)
+        .map(|item| (Keys::of(&text(&item), rules), item))
+        .collect();
+    keyed.sort_by(|(a, _), (b, _)| {
+        rules
+            .iter()
+            .map(|rule| rule.compare(a, b))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or(Ordering::Equal)
+    });
+    items.extend(keyed.into_iter().map(|(_, item)| item));
+}
+
+impl Rule {
+    fn compare(&self, a: &Keys, b: &Keys) -> Ordering {
+        let ordering = match &self.key {
+            // matching snippets first, or last if descending
+            Key::Hashtagged => b.hashtagged.cmp(&a.hashtagged),
+            Key::Tag(tag) => has_tag(b, tag).cmp(&has_tag(a, tag)),
+            // undated and unprioritised snippets go last either way
+            Key::Priority => return self.compare_present(priority(a), priority(b)),
+            Key::Date => return self.compare_present(a.date, b.date),
+            Key::Heading => a.heading.to_lowercase().cmp(&b.heading.to_lowercase()),
+            Key::Length => a.lines.cmp(&b.lines),
+        };
+        self.direct(ordering)
+    }
+
+    fn compare_present<T: Ord>(&self, a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => self.direct(a.cmp(&b)),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+
+    fn direct(&self, ordering: Ordering) -> Ordering {
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+/// The snippet's heading text without the `=` markers.
+fn heading(document: &Document) -> String {
+    document
+        .sections
+        .first()
+        .map(|section| section.title_text())
+        .unwrap_or_default()
+}
+
+fn has_tag(snippet: &Keys, tag: &str) -> bool {
+    snippet.tags.iter().any(|t| t == tag)
+}
+
+/// The most urgent `#p0`..`#p9` tag.
+fn priority(snippet: &Keys) -> Option<u32> {
+    snippet
+        .tags
+        .iter()
+        .filter_map(|tag| tag.strip_prefix('p'))
+        .filter(|level| level.len() == 1)
+        .filter_map(|level| level.parse().ok())
+        .min()
+}
+
+/// The first `YYYY-MM-DD` date in a heading, as (year, month, day).
+pub fn heading_date(heading: &str) -> Option<(u32, u32, u32)> {
+    let caps = DATE_RE.captures(heading)?;
+    Some((
+        caps[1].parse().ok()?,
+        caps[2].parse().ok()?,
+        caps[3].parse().ok()?,
+    ))
+}
@@ -0,0 +1,128 @@
+use crate::document;
+use std::borrow::Cow;
+
+/// Where a pinned snippet stays, from its `#pin`, `#pin-top` or
+/// `#pin-bottom` tag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Pin {
+    Top,
+    /// At its current position
+    Here,
+    Bottom,
+}
+
+fn pin(snippet: &str) -> Option<Pin> {
+    // parsing is slow, and most snippets aren't pinned
+    if !snippet.contains("#pin") {
+        return None;
+    }
+    document::parse(snippet)
+        .hashtags()
+        .iter()
+        .find_map(|tag| match *tag {
+            "pin-top" => Some(Pin::Top),
+            "pin" => Some(Pin::Here),
+            "pin-bottom" => Some(Pin::Bottom),
+            _ => None,
+        })
+}
+
+/// Reorder the unpinned snippets with `arrange`, then put the pinned ones
+/// back: `#pin-top` snippets first and `#pin-bottom` ones last, each in
+/// their original order, and `#pin` ones at their original positions.
+///
+/// `T` is anything standing for a snippet, read with `text`.
+pub fn arrange_unpinned<T>(
+    snippets: Vec<T>,
+    text: impl Fn(&T) -> Cow<'_, str>,
+    arrange: impl FnOnce(Vec<T>) -> Vec<T>,
+) -> Vec<T> {
+    let mut top = Vec::new();
+    let mut here = Vec::new();
+    let mut bottom = Vec::new();
+    let mut unpinned = Vec::new();
+    for (index, snippet) in snippets.into_iter().enumerate() {
+        match pin(&text(&snippet)) {
+            Some(Pin::Top) => top.push(snippet),
+            Some(Pin::Here) => here.push((index, snippet)),
+            Some(Pin::Bottom) => bottom.push(snippet),
+            None => unpinned.push(snippet),
+        }
+    }
+
+    let mut arranged = top;
+    arranged.extend(arrange(unpinned));
+    arranged.extend(bottom);
+    // ascending, so earlier insertions keep later positions right
+    for (index, snippet) in here {
+        arranged.insert(index.min(arranged.len()), snippet);
+    }
+    arranged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reversed_unpinned(snippets: &[&'static str]) -> Vec<&'static str> {
+        arrange_unpinned(
+            snippets.to_vec(),
+            |s| Cow::Borrowed(*s),
+            |mut unpinned| {
+                unpinned.reverse();
+                unpinned
+            },
+        )
+    }
+
+    #[test]
+    fn top_here_and_bottom_pins_together() {
+        let snippets = [
+            "=== a\n",
+            "=== top one #pin-top\n",
+            "=== here #pin\n",
+            "=== b\n",
+            "=== bottom #pin-bottom\n",
+            "=== c\n",
+            "=== top two\n#pin-top\n",
+        ];
+        assert_eq!(
+            reversed_unpinned(&snippets),
+            [
+                "=== top one #pin-top\n",
+                "=== top two\n#pin-top\n",
+                "=== here #pin\n",
+                "=== c\n",
+                "=== b\n",
+                "=== a\n",
+                "=== bottom #pin-bottom\n",
+            ]
+        );
+    }
+
+    #[test]
+    fn pin_past_the_end_goes_last() {
+        let snippets = vec!["=== a\n", "=== b\n", "=== p #pin\n", "=== q #pin\n"];
+        // as when deduping drops snippets
+        let arranged = arrange_unpinned(
+            snippets,
+            |s| Cow::Borrowed(*s),
+            |mut unpinned| {
+                unpinned.truncate(1);
+                unpinned
+            },
+        );
+        assert_eq!(arranged, ["=== a\n", "=== p #pin\n", "=== q #pin\n"]);
+    }
+
+    #[test]
+    fn longer_tags_starting_pin_dont_pin() {
+        assert_eq!(pin("=== a #pinned\n"), None);
+        assert_eq!(pin("=== a #pinboard #pin-topmost\n"), None);
+        assert_eq!(pin("```\n#pin\n```\n"), None);
+        assert_eq!(
+            reversed_unpinned(&["=== a #pinned\n", "=== b\n"]),
+            ["=== b\n", "=== a #pinned\n"]
+        );
+    }
+}
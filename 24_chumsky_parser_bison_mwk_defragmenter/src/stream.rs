@@ -0,0 +1,235 @@
+use crate::order::{self, Rule};
+use crate::pin;
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::ops::{Deref, Range};
+use std::path::Path;
+
+/// A notes file's bytes, memory-mapped when it's a regular file so even
+/// very large exports are paged in rather than copied.
+pub enum Input {
+    Mapped(Mmap),
+    Read(Vec<u8>),
+}
+
+impl Deref for Input {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Mapped(map) => map,
+            Input::Read(bytes) => bytes,
+        }
+    }
+}
+
+pub fn open(path: &Path) -> io::Result<Input> {
+    let file = File::open(path)?;
+    // empty files can't be mapped
+    if file.metadata()?.len() == 0 {
+        return Ok(Input::Read(Vec::new()));
+    }
+    // the notes shouldn't change underneath us; if they do, the worst case
+    // is a garbled output that --in-place would have backed up first
+    Ok(Input::Mapped(unsafe { Mmap::map(&file)? }))
+}
+
+/// Stdin, mapped if it's redirected from a file and read otherwise.
+pub fn stdin() -> io::Result<Input> {
+    let stdin = io::stdin();
+    if let Ok(map) = unsafe { Mmap::map(&stdin) } {
+        if !map.is_empty() {
+            return Ok(Input::Mapped(map));
+        }
+    }
+    let mut bytes = Vec::new();
+    stdin.lock().read_to_end(&mut bytes)?;
+    Ok(Input::Read(bytes))
+}
+
+/// Where each snippet is: the same `=== ` headings outside code fences as
+/// `document::parse` finds, but scanned as bytes, so it needs no copies and
+/// copes with text that isn't UTF-8. Returns the prefix before the first
+/// snippet and each snippet's range, the last running to the end.
+pub fn snippet_ranges(bytes: &[u8]) -> (Range<usize>, Vec<Range<usize>>) {
+    let mut starts = Vec::new();
+    let mut in_fence = false;
+    let mut offset = 0;
+    for line in bytes.split_inclusive(|&b| b == b'\n') {
+        let text = line.strip_suffix(b"\n").unwrap_or(line);
+        let text = text.strip_suffix(b"\r").unwrap_or(text);
+        if in_fence {
+            in_fence = !text.trim_ascii_start().starts_with(b"```");
+        } else if text.starts_with(b"```") {
+            in_fence = true;
+        } else if is_snippet_heading(text) {
+            starts.push(offset);
+        }
+        offset += line.len();
+    }
+
+    let Some(&first) = starts.first() else {
+        return (0..bytes.len(), Vec::new());
+    };
+    let ends = starts.iter().skip(1).copied().chain([bytes.len()]);
+    let ranges = starts
+        .iter()
+        .zip(ends)
+        .map(|(&start, end)| start..end)
+        .collect();
+    (0..first, ranges)
+}
+
+/// Exactly three `=` then a space or tab.
+fn is_snippet_heading(line: &[u8]) -> bool {
+    let marks = line.iter().take_while(|&&b| b == b'=').count();
+    marks == crate::SNIPPET_LEVEL && matches!(line.get(marks), Some(b' ' | b'\t'))
+}
+
+/// A file defragmented by reordering byte ranges of the input rather than
+/// building new text.
+pub struct Plan {
+    prefix: Range<usize>,
+    pieces: Vec<Range<usize>>,
+    /// The input's last line has no line ending
+    missing_newline: bool,
+    pub snippets: usize,
+    pub moved: usize,
+}
+
+/// A snippet's offset in the input, and its bytes.
+type Snippet<'a> = (usize, &'a [u8]);
+
+fn decode<'a>((_, snippet): &'a Snippet) -> Cow<'a, str> {
+    String::from_utf8_lossy(snippet)
+}
+
+/// Sort the snippets in `bytes` by `rules`, keeping pinned ones in place.
+/// Only the sort keys are held in memory; snippets are decoded one at a
+/// time to work them out, with bytes that aren't UTF-8 replaced.
+pub fn plan(bytes: &[u8], rules: &[Rule]) -> Plan {
+    let (prefix, ranges) = snippet_ranges(bytes);
+    let original: Vec<usize> = ranges.iter().map(|range| range.start).collect();
+    let snippets: Vec<Snippet> = ranges
+        .into_iter()
+        .map(|range| (range.start, &bytes[range]))
+        .collect();
+
+    let snippets = pin::arrange_unpinned(snippets, decode, |mut snippets| {
+        order::sort_by_text(&mut snippets, rules, decode);
+        snippets
+    });
+    let pieces: Vec<Range<usize>> = snippets
+        .iter()
+        .map(|&(start, snippet)| start..start + snippet.len())
+        .collect();
+
+    // snippets outside the longest run left in order have moved
+    let arranged: Vec<usize> = pieces.iter().map(|range| range.start).collect();
+    let in_order: usize =
+        similar::capture_diff_slices(similar::Algorithm::Myers, &original, &arranged)
+            .iter()
+            .filter_map(|op| match op {
+                similar::DiffOp::Equal { len, .. } => Some(len),
+                _ => None,
+            })
+            .sum();
+    Plan {
+        prefix,
+        missing_newline: !bytes.is_empty() && !bytes.ends_with(b"\n"),
+        snippets: pieces.len(),
+        moved: pieces.len() - in_order,
+        pieces,
+    }
+}
+
+impl Plan {
+    /// Write the defragmented file, copying each piece straight from `bytes`.
+    pub fn write_to(&self, bytes: &[u8], out: &mut dyn Write) -> io::Result<()> {
+        out.write_all(&bytes[self.prefix.clone()])?;
+        let last = self.pieces.len().saturating_sub(1);
+        for (index, range) in self.pieces.iter().enumerate() {
+            let piece = &bytes[range.clone()];
+            if index < last {
+                // the input's unterminated last line, moved up
+                out.write_all(piece)?;
+                if !piece.ends_with(b"\n") {
+                    out.write_all(b"\n")?;
+                }
+            } else if self.missing_newline && piece.ends_with(b"\n") {
+                out.write_all(&piece[..piece.len() - 1])?;
+            } else {
+                out.write_all(piece)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Lossily decoded, for showing in a diff.
+    pub fn to_text(&self, bytes: &[u8]) -> String {
+        let mut out = Vec::with_capacity(bytes.len());
+        self.write_to(bytes, &mut out)
+            .expect("writing to a Vec can't fail");
+        String::from_utf8_lossy(&out).into_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{defragment, Options, Output};
+
+    /// The defragmented text both ways: reordering byte ranges, and
+    /// rebuilding the text from snippet strings.
+    fn both_ways(input: &str) -> (String, String) {
+        let options = Options {
+            rules: order::default_rules(),
+            grouping: None,
+            dedupe: None,
+            similarity: crate::dedupe::DEFAULT_SIMILARITY,
+            archive_before: None,
+        };
+        let streamed = plan(input.as_bytes(), &options.rules).to_text(input.as_bytes());
+        let Output::Text(rebuilt) = defragment(input, &options, None).output else {
+            unreachable!("defragment rebuilds the text");
+        };
+        (streamed, rebuilt)
+    }
+
+    #[test]
+    fn terminated_input_matches_the_text_path() {
+        let input = "intro\n=== b\ntwo\n=== here #pin\n=== a\n#tag\none\n";
+        let (streamed, rebuilt) = both_ways(input);
+        assert_eq!(streamed, rebuilt);
+        assert_eq!(
+            streamed,
+            "intro\n=== a\n#tag\none\n=== here #pin\n=== b\ntwo\n"
+        );
+    }
+
+    #[test]
+    fn unterminated_input_matches_the_text_path() {
+        // the last snippet moves up and needs a line ending, which the one
+        // now last gives up
+        let (streamed, rebuilt) = both_ways("=== b\ntwo\n=== a\n#tag\none");
+        assert_eq!(streamed, rebuilt);
+        assert_eq!(streamed, "=== a\n#tag\none\n=== b\ntwo");
+
+        // nothing moves
+        let (streamed, rebuilt) = both_ways("=== a\n#tag\none\n=== b\ntwo");
+        assert_eq!(streamed, rebuilt);
+        assert_eq!(streamed, "=== a\n#tag\none\n=== b\ntwo");
+    }
+
+    #[test]
+    fn bytes_that_arent_utf8_are_copied() {
+        let input = b"=== b\nt\xffo\n=== a\n#tag\non\xe9\n";
+        let plan = plan(input, &order::default_rules());
+        assert_eq!(plan.moved, 1);
+        let mut out = Vec::new();
+        plan.write_to(input, &mut out).unwrap();
+        assert_eq!(out, b"=== a\n#tag\non\xe9\n=== b\nt\xffo\n");
+    }
+}